@@ -5,18 +5,16 @@ import * as game from "../../pkg";
 
 const app = game.App.new();
 
-// const gameLoop: FrameRequestCallback = (now) => {
-//   const dt = now - start;
-//   app.update(dt);
-//   start = now;
-//   requestAnimationFrame(gameLoop);
-// };
+// The simulation runs in fixed time steps, so the real frame time is passed as is.
+let last = performance.now();
 
-// gameLoop(start);
+const gameLoop: FrameRequestCallback = (now) => {
+  app.update(now - last);
+  last = now;
+  requestAnimationFrame(gameLoop);
+};
 
-setInterval(() => {
-  app.update(1000 / 60);
-}, 1000 / 60);
+requestAnimationFrame(gameLoop);
 
 function App() {
   return <></>;
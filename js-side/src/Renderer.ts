@@ -1,5 +1,14 @@
+// Must match `render.rs`.
+const GEOMETRY_STRIDE = 4;
+const SHAPE_RECT = 0;
+const SHAPE_CIRCLE = 1;
+
+// Colors drift as traits evolve, so the cache is dropped between frames once it grows this large.
+const MAX_COLOR_STYLES = 4096;
+
 export class Renderer {
   private ctx: CanvasRenderingContext2D;
+  private colorStyles: Map<number, string> = new Map();
 
   constructor() {
     const canvas = document.createElement("canvas");
@@ -59,6 +68,43 @@ export class Renderer {
     this.ctx.restore();
   }
 
+  // Draws a frame of commands. `geometry` and `colors` are views into wasm memory, they are only
+  // valid during this call.
+  public draw(geometry: Float32Array, colors: Uint32Array) {
+    this.clear();
+
+    if (this.colorStyles.size > MAX_COLOR_STYLES) {
+      this.colorStyles.clear();
+    }
+
+    for (let i = 0; i < colors.length; i++) {
+      const offset = i * GEOMETRY_STRIDE;
+      const shape = geometry[offset];
+      const x = geometry[offset + 1];
+      const y = geometry[offset + 2];
+      const size = geometry[offset + 3];
+      const color = this.colorStyle(colors[i]);
+
+      if (shape === SHAPE_CIRCLE) {
+        this.circle(x, y, size, color);
+      } else if (shape === SHAPE_RECT) {
+        this.rect(x, y, size, size, color);
+      }
+    }
+  }
+
+  // Converts a packed 0xRRGGBBAA color to a CSS color, caching the strings.
+  private colorStyle(rgba: number): string {
+    let style = this.colorStyles.get(rgba);
+
+    if (style === undefined) {
+      style = "#" + (rgba >>> 0).toString(16).padStart(8, "0");
+      this.colorStyles.set(rgba, style);
+    }
+
+    return style;
+  }
+
   public circle(x: number, y: number, radius: number, color: string) {
     this.ctx.beginPath();
     this.ctx.arc(x, y, radius, 0, 2 * Math.PI, false);
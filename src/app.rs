@@ -0,0 +1,416 @@
+use glam::Vec2;
+use wasm_bindgen::prelude::*;
+
+use crate::config::SimConfig;
+use crate::entity::Plant;
+use crate::id::Id;
+use crate::render::{draw_world, species_color, ColorMode, RenderBuffer};
+use crate::simulation::Simulation;
+use crate::stats::{Trait, HISTOGRAM_BINS};
+use crate::terrain::TerrainBlock;
+use crate::utils;
+use crate::world::screen_to_world;
+
+#[wasm_bindgen(module = "/js-side/src/Renderer.ts")]
+extern "C" {
+    type Renderer;
+    // Import the constructor for the Renderer class.
+    #[wasm_bindgen(constructor)]
+    fn new() -> Renderer;
+    // Clears the canvas and draws a `RenderBuffer`, see `render.rs` for the layout.
+    #[wasm_bindgen(method)]
+    fn draw(this: &Renderer, geometry: &[f32], colors: &[u32]);
+    #[wasm_bindgen(method, js_name = moveCamera)]
+    fn move_camera(this: &Renderer, x: f32, y: f32);
+    #[wasm_bindgen(method, js_name = zoomCamera)]
+    fn zoom(this: &Renderer, zoom: f32, x: f32, y: f32);
+    #[wasm_bindgen(method, js_name = getScale)]
+    fn get_scale(this: &Renderer) -> f32;
+    #[wasm_bindgen(method, js_name = getCameraX)]
+    fn get_camera_x(this: &Renderer) -> f32;
+    #[wasm_bindgen(method, js_name = getCameraY)]
+    fn get_camera_y(this: &Renderer) -> f32;
+}
+
+#[wasm_bindgen(module = "/js-side/src/Input.ts")]
+extern "C" {
+    type Input;
+    #[wasm_bindgen(static_method_of = Input)]
+    fn init();
+    #[wasm_bindgen(static_method_of = Input, js_name = isDown)]
+    fn is_down(key: &str) -> bool;
+    #[wasm_bindgen(static_method_of = Input, js_name = isMouseDown)]
+    fn is_mouse_down() -> bool;
+    #[wasm_bindgen(static_method_of = Input, js_name = getWheel)]
+    fn get_wheel() -> f32;
+    #[wasm_bindgen(static_method_of = Input, js_name = getMouseX)]
+    fn get_mouse_x() -> f32;
+    #[wasm_bindgen(static_method_of = Input, js_name = getMouseY)]
+    fn get_mouse_y() -> f32;
+}
+
+#[wasm_bindgen]
+pub struct App {
+    simulation: Simulation,
+    renderer: Renderer,
+    render_buffer: RenderBuffer,
+    color_mode: ColorMode,
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str);
+}
+
+#[wasm_bindgen]
+impl App {
+    /// Creates an app with a random seed.
+    pub fn new() -> Self {
+        Self::with_seed(rand::random())
+    }
+
+    /// Creates an app whose simulation is reproducible from `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_simulation(Simulation::new(seed))
+    }
+
+    /// Creates an app tuned by a JSON `SimConfig`. Missing fields keep their default value.
+    pub fn with_config(seed: u64, config_json: &str) -> Result<App, JsValue> {
+        let config = SimConfig::from_json(config_json).map_err(|error| error.to_string())?;
+        Ok(Self::with_simulation(Simulation::with_config(seed, config)))
+    }
+
+    /// The current config as JSON.
+    pub fn config(&self) -> String {
+        self.simulation.config().to_json()
+    }
+
+    /// Advances the simulation by the `dt` milliseconds since the last frame, in fixed time steps,
+    /// then draws it.
+    pub fn update(&mut self, dt: f32) {
+        self.simulation.advance(dt);
+
+        self.draw();
+        self.handle_input();
+    }
+
+    /// Fraction of the year gone by, in [0, 1).
+    pub fn year_phase(&self) -> f32 {
+        self.simulation.clock().year_phase
+    }
+
+    /// "spring", "summer", "autumn" or "winter", or `undefined` when the world has no seasons.
+    pub fn season(&self) -> Option<String> {
+        let season = self.simulation.clock().season?;
+        Some(season.name().to_string())
+    }
+
+    /// Fraction of the day gone by, in [0, 1): 0 at sunrise, 0.25 at noon, 0.5 at sunset and
+    /// 0.75 at midnight.
+    pub fn day_phase(&self) -> f32 {
+        self.simulation.clock().day_phase
+    }
+
+    /// How bright it is, from 0 at midnight to 1 at noon, to tint the scene with.
+    pub fn daylight(&self) -> f32 {
+        self.simulation.clock().daylight
+    }
+
+    /// Saves the world as a binary snapshot.
+    pub fn save(&self) -> Vec<u8> {
+        self.simulation.save()
+    }
+
+    /// Replaces the world with a binary snapshot made by `save`.
+    pub fn load(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.simulation = Simulation::load(bytes).map_err(|error| error.to_string())?;
+        Ok(())
+    }
+
+    /// Saves the world as a JSON snapshot.
+    pub fn save_json(&self) -> String {
+        self.simulation.save_json()
+    }
+
+    /// Replaces the world with a JSON snapshot made by `save_json`.
+    pub fn load_json(&mut self, json: &str) -> Result<(), JsValue> {
+        self.simulation = Simulation::load_json(json).map_err(|error| error.to_string())?;
+        Ok(())
+    }
+
+    /// The id of every entity, in the order they are drawn. Ids are `u64`s (`BigInt` in JS) that
+    /// stay valid for the life of the entity and are never reused.
+    pub fn entity_ids(&self) -> Vec<u64> {
+        self.simulation
+            .entities()
+            .iter()
+            .map(|entity| entity.id().to_bits())
+            .collect()
+    }
+
+    /// The current index of the entity `id`, or `undefined` if it is dead.
+    pub fn entity_index(&self, id: u64) -> Option<usize> {
+        self.simulation.entity_index(Id::from_bits(id))
+    }
+
+    /// The id of the parent of the entity `id`, or `undefined` if it is dead or has no parent.
+    pub fn entity_parent_id(&self, id: u64) -> Option<u64> {
+        let entity = self.simulation.entity(Id::from_bits(id))?;
+        entity.parent_id().map(Id::to_bits)
+    }
+
+    /// The id of the mate of the parent of the entity `id`, or `undefined` if it is dead or was
+    /// born from a single parent.
+    pub fn entity_other_parent_id(&self, id: u64) -> Option<u64> {
+        let entity = self.simulation.entity(Id::from_bits(id))?;
+        entity.other_parent_id().map(Id::to_bits)
+    }
+
+    /// The species of the entity `id`, or `undefined` if it is dead or hasn't been clustered yet.
+    pub fn entity_species(&self, id: u64) -> Option<u32> {
+        self.simulation.entity(Id::from_bits(id))?.species()
+    }
+
+    /// The living species as JSON: id, birth tick, population and centroid of each.
+    pub fn species_json(&self) -> String {
+        self.simulation
+            .species()
+            .to_json(self.simulation.genome_layout())
+    }
+
+    /// The color entities of species `id` are drawn with, as `0xRRGGBBAA`.
+    pub fn species_color(&self, id: u32) -> u32 {
+        species_color(id)
+    }
+
+    /// Colors entities by "species" (the default), by "traits" or by "diet".
+    pub fn set_color_mode(&mut self, mode: &str) -> Result<(), JsValue> {
+        self.color_mode = ColorMode::from_name(mode)
+            .ok_or_else(|| JsValue::from(format!("unknown color mode {}", mode)))?;
+        Ok(())
+    }
+
+    /// The name of every gene, in the order of `entity_genome`.
+    pub fn gene_names(&self) -> Vec<String> {
+        self.simulation
+            .genome_layout()
+            .genes()
+            .iter()
+            .map(|gene| gene.name.clone())
+            .collect()
+    }
+
+    /// The genes of the entity `id`, or `undefined` if it is dead.
+    pub fn entity_genome(&self, id: u64) -> Option<Vec<f32>> {
+        let entity = self.simulation.entity(Id::from_bits(id))?;
+        Some(entity.genome().values().to_vec())
+    }
+
+    pub fn plant_ids(&self) -> Vec<u64> {
+        self.simulation
+            .plants()
+            .iter()
+            .map(|plant| plant.id().to_bits())
+            .collect()
+    }
+
+    /// The current index of the plant `id`, or `undefined` if it has been eaten.
+    pub fn plant_index(&self, id: u64) -> Option<usize> {
+        self.simulation.plant_index(Id::from_bits(id))
+    }
+
+    /// The age in ticks of the plant `id`, or `undefined` if it has been eaten or died.
+    pub fn plant_age(&self, id: u64) -> Option<u64> {
+        Some(self.simulation.plant(Id::from_bits(id))?.age())
+    }
+
+    /// The name of every plant gene, in the order of `plant_genome`. Empty unless plants evolve.
+    pub fn plant_gene_names(&self) -> Vec<String> {
+        self.simulation
+            .plant_layout()
+            .genes()
+            .iter()
+            .map(|gene| gene.name.clone())
+            .collect()
+    }
+
+    /// The genes of the plant `id`, or `undefined` if it has been eaten or died.
+    pub fn plant_genome(&self, id: u64) -> Option<Vec<f32>> {
+        let plant = self.simulation.plant(Id::from_bits(id))?;
+        Some(plant.genome().values().to_vec())
+    }
+
+    /// The family tree of every entity that ever lived, in Newick format.
+    pub fn lineage_newick(&self) -> String {
+        self.simulation.lineage().to_newick()
+    }
+
+    /// The family tree of every entity that ever lived, as a JSON array of records.
+    pub fn lineage_json(&self) -> String {
+        self.simulation
+            .lineage()
+            .to_json(self.simulation.genome_layout())
+    }
+
+    /// Forgets the dead entities that have no living descendant. Returns how many were removed.
+    pub fn prune_lineage(&mut self) -> usize {
+        self.simulation.lineage_mut().prune_extinct()
+    }
+
+    /// The number of plants inside every terrain block, row by row (`x` major).
+    pub fn plant_counts(&self) -> Vec<u32> {
+        self.terrain_field(TerrainBlock::number_of_plants)
+    }
+
+    /// The biome of every terrain block, row by row (`x` major): 0 for water, 1 for desert, 2 for
+    /// grassland and 3 for forest.
+    pub fn biomes(&self) -> Vec<u8> {
+        self.terrain_field(|block| block.biome() as u8)
+    }
+
+    /// The elevation of every terrain block, row by row (`x` major).
+    pub fn elevations(&self) -> Vec<f32> {
+        self.terrain_field(TerrainBlock::elevation)
+    }
+
+    /// The moisture of every terrain block, row by row (`x` major).
+    pub fn moistures(&self) -> Vec<f32> {
+        self.terrain_field(TerrainBlock::moisture)
+    }
+
+    /// The number of plants inside the terrain block at grid coordinates `x`, `y`.
+    pub fn block_plant_count(&self, x: usize, y: usize) -> Option<u32> {
+        let blocks = self.simulation.terrain().blocks();
+        Some(blocks.get(x)?.get(y)?.number_of_plants())
+    }
+
+    /// The tick of every recorded stats sample, oldest first.
+    pub fn stats_ticks(&self) -> Vec<f64> {
+        self.simulation.stats().series(|sample| sample.tick as f64)
+    }
+
+    pub fn stats_population(&self) -> Vec<u32> {
+        self.simulation.stats().series(|sample| sample.population)
+    }
+
+    pub fn stats_plants(&self) -> Vec<u32> {
+        self.simulation.stats().series(|sample| sample.plants)
+    }
+
+    pub fn stats_total_energy(&self) -> Vec<f32> {
+        self.simulation.stats().series(|sample| sample.total_energy)
+    }
+
+    /// The mean of `trait_name` ("size", "sense_distance" or "velocity_mag") per sample.
+    pub fn stats_mean(&self, trait_name: &str) -> Result<Vec<f32>, JsValue> {
+        let t = parse_trait(trait_name)?;
+        Ok(self
+            .simulation
+            .stats()
+            .series(|sample| sample.trait_stats(t).mean))
+    }
+
+    pub fn stats_variance(&self, trait_name: &str) -> Result<Vec<f32>, JsValue> {
+        let t = parse_trait(trait_name)?;
+        Ok(self
+            .simulation
+            .stats()
+            .series(|sample| sample.trait_stats(t).variance))
+    }
+
+    /// The histograms of `trait_name` per sample, flattened: `stats_histogram_bins()` counts per
+    /// sample.
+    pub fn stats_histogram(&self, trait_name: &str) -> Result<Vec<u32>, JsValue> {
+        Ok(self.simulation.stats().histograms(parse_trait(trait_name)?))
+    }
+
+    pub fn stats_histogram_bins(&self) -> usize {
+        HISTOGRAM_BINS
+    }
+
+    pub fn stats_csv(&self) -> String {
+        self.simulation.stats().to_csv()
+    }
+}
+
+impl App {
+    fn with_simulation(simulation: Simulation) -> Self {
+        utils::set_panic_hook();
+        let renderer = Renderer::new();
+        Input::init();
+
+        Self {
+            simulation,
+            renderer,
+            render_buffer: RenderBuffer::new(),
+            color_mode: ColorMode::default(),
+        }
+    }
+
+    // One value per terrain block, row by row (`x` major).
+    fn terrain_field<T>(&self, value: impl Fn(&TerrainBlock) -> T) -> Vec<T> {
+        self.simulation
+            .terrain()
+            .blocks()
+            .iter()
+            .flatten()
+            .map(value)
+            .collect()
+    }
+
+    fn draw(&mut self) {
+        draw_world(&self.simulation, self.color_mode, &mut self.render_buffer);
+
+        self.renderer
+            .draw(self.render_buffer.geometry(), self.render_buffer.colors());
+    }
+
+    fn handle_input(&mut self) {
+        // Add WASD movement
+        let mut movement = Vec2::new(0.0, 0.0);
+        if Input::is_down("w") {
+            movement.y += 15.0;
+        }
+        if Input::is_down("s") {
+            movement.y -= 15.0;
+        }
+        if Input::is_down("a") {
+            movement.x += 15.0;
+        }
+        if Input::is_down("d") {
+            movement.x -= 15.0;
+        }
+        self.renderer.move_camera(movement.x, movement.y);
+
+        if Input::is_mouse_down() {
+            // Add a new plant
+            let mouse = Vec2::new(Input::get_mouse_x(), Input::get_mouse_y());
+            let camera = Vec2::new(self.renderer.get_camera_x(), self.renderer.get_camera_y());
+            let position = screen_to_world(mouse, camera, self.renderer.get_scale());
+
+            self.simulation.add_plant(Plant::new(position, 0.1));
+        }
+
+        // Zoom
+        let wheel = Input::get_wheel();
+
+        if wheel > 0.0 {
+            self.renderer
+                .zoom(0.9, Input::get_mouse_x(), Input::get_mouse_y());
+        } else if wheel < 0.0 {
+            self.renderer
+                .zoom(1.1, Input::get_mouse_x(), Input::get_mouse_y());
+        }
+    }
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_trait(name: &str) -> Result<Trait, JsValue> {
+    Trait::from_name(name).ok_or_else(|| JsValue::from(format!("unknown trait {}", name)))
+}
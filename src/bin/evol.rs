@@ -0,0 +1,305 @@
+//! Runs the simulation headless, for batch experiments.
+//!
+//! ```text
+//! evol --ticks 100000 --seed 7 --config config.json --out runs/7
+//! ```
+//!
+//! Writes into the output directory:
+//!
+//! - `config.json`: the config the world was created with.
+//! - `stats.csv`: a row of `Stats` every `--stats-every` ticks and at the end.
+//! - `snapshots/tick_<tick>.evol`: a binary snapshot every `--snapshot-every` ticks, if set.
+//! - `final.evol`: a binary snapshot of the world at the end.
+//! - `lineage.nwk` and `lineage.json`: every entity that ever lived, see `Lineage`.
+//! - `species.json`: the species at the end.
+//!
+//! The run stops early when every entity has died.
+//!
+//! ```text
+//! evol sweep experiment.json --out runs/sweep
+//! ```
+//!
+//! Runs every point and seed of an `Experiment`, in parallel, and writes `summary.csv` (one row
+//! per point, see `ExperimentResults::to_csv`) and `runs.csv` (one row per run).
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Instant;
+
+use game::{Experiment, SimConfig, Simulation, Stats};
+
+const USAGE: &str = "usage: evol --ticks N [options]
+       evol sweep EXPERIMENT [--out DIR] [--threads N]
+
+options:
+    --ticks N            number of steps to run
+    --seed N             seed of the world (default 0)
+    --config PATH        JSON SimConfig, missing fields keep their default
+    --entities N         overrides initial_entities of the config
+    --plants N           overrides initial_plants of the config
+    --out DIR            output directory (default out)
+    --stats-every N      ticks between two rows of stats.csv (default 60)
+    --snapshot-every N   ticks between two snapshots, 0 for the final one only (default 0)
+
+sweep options:
+    --out DIR            output directory (default out)
+    --threads N          runs at the same time, 0 for one per core (default 0)";
+
+struct Options {
+    ticks: u64,
+    seed: u64,
+    config: Option<PathBuf>,
+    entities: Option<usize>,
+    plants: Option<usize>,
+    out: PathBuf,
+    stats_every: u64,
+    snapshot_every: u64,
+}
+
+struct SweepOptions {
+    experiment: PathBuf,
+    out: PathBuf,
+    threads: usize,
+}
+
+fn main() {
+    let mut args = env::args().skip(1).peekable();
+    let result = if args.peek().map(String::as_str) == Some("sweep") {
+        args.next();
+        parse_sweep_options(args).and_then(|options| sweep(&options))
+    } else {
+        parse_options(args).and_then(|options| run(&options))
+    };
+
+    if let Err(message) = result {
+        eprintln!("evol: {}", message);
+        process::exit(1);
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        ticks: 0,
+        seed: 0,
+        config: None,
+        entities: None,
+        plants: None,
+        out: PathBuf::from("out"),
+        stats_every: 60,
+        snapshot_every: 0,
+    };
+    let mut ticks = None;
+
+    while let Some(flag) = args.next() {
+        if flag == "-h" || flag == "--help" {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value\n\n{}", flag, USAGE))?;
+
+        match flag.as_str() {
+            "--ticks" => ticks = Some(parse_number(&flag, &value)?),
+            "--seed" => options.seed = parse_number(&flag, &value)?,
+            "--config" => options.config = Some(PathBuf::from(value)),
+            "--entities" => options.entities = Some(parse_number(&flag, &value)?),
+            "--plants" => options.plants = Some(parse_number(&flag, &value)?),
+            "--out" => options.out = PathBuf::from(value),
+            "--stats-every" => options.stats_every = parse_number(&flag, &value)?,
+            "--snapshot-every" => options.snapshot_every = parse_number(&flag, &value)?,
+            _ => return Err(format!("unknown option {}\n\n{}", flag, USAGE)),
+        }
+    }
+
+    options.ticks = ticks.ok_or_else(|| format!("--ticks is required\n\n{}", USAGE))?;
+    if options.stats_every == 0 {
+        return Err("--stats-every must be at least 1".to_string());
+    }
+
+    Ok(options)
+}
+
+fn parse_sweep_options(mut args: impl Iterator<Item = String>) -> Result<SweepOptions, String> {
+    let mut experiment = None;
+    let mut options = SweepOptions {
+        experiment: PathBuf::new(),
+        out: PathBuf::from("out"),
+        threads: 0,
+    };
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+
+        if !arg.starts_with('-') && experiment.is_none() {
+            experiment = Some(PathBuf::from(arg));
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value\n\n{}", arg, USAGE))?;
+
+        match arg.as_str() {
+            "--out" => options.out = PathBuf::from(value),
+            "--threads" => options.threads = parse_number(&arg, &value)?,
+            _ => return Err(format!("unknown option {}\n\n{}", arg, USAGE)),
+        }
+    }
+
+    options.experiment =
+        experiment.ok_or_else(|| format!("sweep needs an experiment file\n\n{}", USAGE))?;
+
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got {}", flag, value))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut config = match &options.config {
+        Some(path) => {
+            let json = fs::read_to_string(path)
+                .map_err(|error| format!("can't read {}: {}", path.display(), error))?;
+            SimConfig::from_json(&json).map_err(|error| format!("{}: {}", path.display(), error))?
+        }
+        None => SimConfig::default(),
+    };
+    config.initial_entities = options.entities.unwrap_or(config.initial_entities);
+    config.initial_plants = options.plants.unwrap_or(config.initial_plants);
+
+    if config.initial_entities == 0 {
+        return Err(
+            "the world has no entities, set initial_entities or pass --entities".to_string(),
+        );
+    }
+
+    fs::create_dir_all(&options.out).map_err(|error| io_error(&options.out, error))?;
+    write(&options.out.join("config.json"), &config.to_json())?;
+
+    let mut simulation = Simulation::with_config(options.seed, config);
+
+    let stats_path = options.out.join("stats.csv");
+    let mut stats =
+        BufWriter::new(File::create(&stats_path).map_err(|error| io_error(&stats_path, error))?);
+    stats
+        .write_all(Stats::csv_header().as_bytes())
+        .map_err(|error| io_error(&stats_path, error))?;
+
+    let started = Instant::now();
+
+    while simulation.tick() < options.ticks && !simulation.entities().is_empty() {
+        simulation.step(simulation.config().time_step);
+        let tick = simulation.tick();
+
+        if tick.is_multiple_of(options.stats_every)
+            || tick == options.ticks
+            || simulation.entities().is_empty()
+        {
+            let sample = simulation
+                .stats()
+                .latest()
+                .expect("a step records a sample");
+            stats
+                .write_all(sample.to_csv_row().as_bytes())
+                .map_err(|error| io_error(&stats_path, error))?;
+        }
+
+        if options.snapshot_every > 0 && tick.is_multiple_of(options.snapshot_every) {
+            let snapshots = options.out.join("snapshots");
+            fs::create_dir_all(&snapshots).map_err(|error| io_error(&snapshots, error))?;
+            write_bytes(
+                &snapshots.join(format!("tick_{}.evol", tick)),
+                &simulation.save(),
+            )?;
+        }
+    }
+
+    stats
+        .flush()
+        .map_err(|error| io_error(&stats_path, error))?;
+
+    // Save the world before clustering it once more for `species.json`, so that it resumes
+    // exactly like an uninterrupted run.
+    write_bytes(&options.out.join("final.evol"), &simulation.save())?;
+    simulation.cluster_species();
+    let layout = simulation.genome_layout();
+    write(
+        &options.out.join("lineage.nwk"),
+        &simulation.lineage().to_newick(),
+    )?;
+    write(
+        &options.out.join("lineage.json"),
+        &simulation.lineage().to_json(layout),
+    )?;
+    write(
+        &options.out.join("species.json"),
+        &simulation.species().to_json(layout),
+    )?;
+
+    let outcome = if simulation.entities().is_empty() {
+        "every entity died"
+    } else {
+        "done"
+    };
+    eprintln!(
+        "{} after {} ticks in {:.1?}: {} entities, {} plants, {} species",
+        outcome,
+        simulation.tick(),
+        started.elapsed(),
+        simulation.entities().len(),
+        simulation.plants().len(),
+        simulation.species().species().len()
+    );
+
+    Ok(())
+}
+
+fn sweep(options: &SweepOptions) -> Result<(), String> {
+    let path = &options.experiment;
+    let json = fs::read_to_string(path)
+        .map_err(|error| format!("can't read {}: {}", path.display(), error))?;
+    let experiment =
+        Experiment::from_json(&json).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    fs::create_dir_all(&options.out).map_err(|error| io_error(&options.out, error))?;
+
+    let started = Instant::now();
+    let results = experiment
+        .run(options.threads)
+        .map_err(|error| error.to_string())?;
+
+    write(&options.out.join("summary.csv"), &results.to_csv())?;
+    write(&options.out.join("runs.csv"), &results.runs_to_csv())?;
+
+    eprintln!(
+        "{} points of {} runs in {:.1?}",
+        results.points.len(),
+        experiment.seeds.len(),
+        started.elapsed()
+    );
+
+    Ok(())
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    write_bytes(path, contents.as_bytes())
+}
+
+fn write_bytes(path: &Path, contents: &[u8]) -> Result<(), String> {
+    fs::write(path, contents).map_err(|error| io_error(path, error))
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> String {
+    format!("{}: {}", path.display(), error)
+}
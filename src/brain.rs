@@ -0,0 +1,110 @@
+//! The neural network that steers entities when `SimConfig::behavior` is `Behavior::Neural`.
+//!
+//! A brain is a feed-forward network with one hidden layer of `tanh` neurons. It has no state
+//! of its own: its weights are genes of the entity (see `GenomeLayout::brain`), so they are
+//! inherited and mutate like any other trait.
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Number of values an entity senses every step, see `Senses::inputs`.
+pub const BRAIN_INPUTS: usize = 8;
+/// Number of values a brain outputs: a steering vector and a speed.
+pub const BRAIN_OUTPUTS: usize = 3;
+/// Genes of brain weights range over `± BRAIN_WEIGHT_LIMIT`.
+pub static BRAIN_WEIGHT_LIMIT: f32 = 4.0;
+
+/// How entities decide where to go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Behavior {
+    /// Flee bigger entities, chase smaller ones, walk to the closest plant, otherwise wander.
+    #[default]
+    Rules,
+    /// Steer with an evolved neural network.
+    Neural,
+}
+
+/// Number of weights, biases included, of a brain with `hidden` hidden neurons.
+pub fn brain_weights(hidden: usize) -> usize {
+    (BRAIN_INPUTS + 1) * hidden + (hidden + 1) * BRAIN_OUTPUTS
+}
+
+/// What an entity perceives of the closest plant and the closest entity within its sense
+/// distance, and of itself. Corpses are not sensed, brains only eat those they touch.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Senses {
+    /// 1 when touching, falling to 0 at the edge of the sense distance or when nothing is seen.
+    pub plant_proximity: f32,
+    /// Unit vector towards the plant, zero when nothing is seen.
+    pub plant_direction: Vec2,
+    pub entity_proximity: f32,
+    pub entity_direction: Vec2,
+    /// How much bigger (positive) or smaller (negative) the other entity is, relative to us.
+    pub entity_relative_size: f32,
+    /// Energy as a fraction of what it takes to have children, at most 1.
+    pub energy: f32,
+}
+
+impl Senses {
+    pub fn inputs(&self) -> [f32; BRAIN_INPUTS] {
+        [
+            self.plant_proximity,
+            self.plant_direction.x,
+            self.plant_direction.y,
+            self.entity_proximity,
+            self.entity_direction.x,
+            self.entity_direction.y,
+            self.entity_relative_size,
+            self.energy,
+        ]
+    }
+}
+
+/// What a brain decides.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    /// Where to go, each coordinate in [-1, 1]. Not normalized.
+    pub steering: Vec2,
+    /// Fraction of the top speed, in [0, 1].
+    pub speed: f32,
+}
+
+/// Runs the network described by `weights` on `senses`.
+///
+/// `weights` holds, for every hidden neuron, its bias followed by one weight per input, then
+/// the same for every output neuron over the hidden layer.
+pub fn think(weights: &[f32], hidden: usize, senses: &Senses) -> Decision {
+    debug_assert_eq!(weights.len(), brain_weights(hidden));
+
+    let inputs = senses.inputs();
+    let (hidden_weights, output_weights) = weights.split_at((BRAIN_INPUTS + 1) * hidden);
+
+    let hidden_values: Vec<f32> = hidden_weights
+        .chunks_exact(BRAIN_INPUTS + 1)
+        .map(|neuron| neuron_sum(neuron, &inputs).tanh())
+        .collect();
+
+    let mut outputs = [0.0; BRAIN_OUTPUTS];
+    for (output, neuron) in outputs
+        .iter_mut()
+        .zip(output_weights.chunks_exact(hidden + 1))
+    {
+        *output = neuron_sum(neuron, &hidden_values);
+    }
+
+    Decision {
+        steering: Vec2::new(outputs[0].tanh(), outputs[1].tanh()),
+        speed: 1.0 / (1.0 + (-outputs[2]).exp()),
+    }
+}
+
+// The bias plus the weighted sum of the inputs.
+fn neuron_sum(neuron: &[f32], inputs: &[f32]) -> f32 {
+    neuron[0]
+        + neuron[1..]
+            .iter()
+            .zip(inputs)
+            .map(|(weight, input)| weight * input)
+            .sum::<f32>()
+}
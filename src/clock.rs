@@ -0,0 +1,105 @@
+//! Seasons and days.
+//!
+//! The time of the year and of the day follow from the tick and the config alone, so the clock
+//! has no state of its own to save.
+
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::SimConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum Season {
+    Spring = 0,
+    Summer = 1,
+    Autumn = 2,
+    Winter = 3,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [
+        Season::Spring,
+        Season::Summer,
+        Season::Autumn,
+        Season::Winter,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Season::Spring => "spring",
+            Season::Summer => "summer",
+            Season::Autumn => "autumn",
+            Season::Winter => "winter",
+        }
+    }
+}
+
+/// The time of the year and of the day at some tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clock {
+    /// Fraction of the year gone by, in [0, 1). Always 0 without seasons.
+    pub year_phase: f32,
+    /// `None` when `SimConfig::year_length` is 0.
+    pub season: Option<Season>,
+    /// Fraction of the day gone by, in [0, 1): 0 at sunrise, 0.25 at noon, 0.5 at sunset and
+    /// 0.75 at midnight. Always 0.25 without days.
+    pub day_phase: f32,
+    /// How bright it is, from 0 at midnight to 1 at noon. Always 1 without days.
+    pub daylight: f32,
+}
+
+impl Clock {
+    pub fn new(tick: u64, config: &SimConfig) -> Self {
+        let (year_phase, season) = match config.year_length {
+            0 => (0.0, None),
+            length => {
+                let phase = (tick % length) as f32 / length as f32;
+                let season = Season::ALL[((phase * 4.0) as usize).min(3)];
+                (phase, Some(season))
+            }
+        };
+
+        let (day_phase, daylight) = match config.day_length {
+            0 => (0.25, 1.0),
+            length => {
+                let phase = (tick % length) as f32 / length as f32;
+                (phase, 0.5 + 0.5 * (2.0 * PI * phase).sin())
+            }
+        };
+
+        Self {
+            year_phase,
+            season,
+            day_phase,
+            daylight,
+        }
+    }
+
+    /// What `SimConfig::plant_growth_rate` is multiplied by.
+    pub fn growth_factor(&self, config: &SimConfig) -> f32 {
+        match self.season {
+            Some(season) => config.season_growth[season as usize],
+            None => 1.0,
+        }
+    }
+
+    /// What `SimConfig::fertility_regeneration` is multiplied by.
+    pub fn regeneration_factor(&self, config: &SimConfig) -> f32 {
+        match self.season {
+            Some(season) => config.season_regeneration[season as usize],
+            None => 1.0,
+        }
+    }
+
+    /// What sense distances are multiplied by: 1 at noon, `SimConfig::night_sense` at midnight.
+    pub fn sense_factor(&self, config: &SimConfig) -> f32 {
+        if config.day_length == 0 {
+            return 1.0;
+        }
+
+        config.night_sense + (1.0 - config.night_sense) * self.daylight
+    }
+}
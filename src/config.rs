@@ -0,0 +1,521 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::brain::Behavior;
+use crate::genome::{GeneKind, GeneSpec, Mutation, BUILT_IN_GENES};
+use crate::terrain::TerrainKind;
+
+/// Every tunable constant of the simulation.
+///
+/// The defaults reproduce the original hand-tuned behavior, with one exception: the fertility of
+/// the land regenerates and diffuses (`fertility_regeneration`, `fertility_diffusion`), so that
+/// barren blocks recover. Both can be set to 0, fertility then only stays within
+/// [0, `MAX_FERTILITY`]. Every other feature added since is off by default.
+///
+/// Fields missing from a JSON config keep their default, unknown fields are an error so that
+/// typos do not go unnoticed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimConfig {
+    /// How far an entity with a `sense_distance` of 1 can see.
+    pub sense_range: f32,
+    /// Length of one simulation step, in milliseconds. `Simulation::advance` always steps by
+    /// this much, whatever the frame rate.
+    pub time_step: f32,
+    /// Speed of an entity with a `velocity_mag` of 1, in world units per millisecond.
+    pub max_speed: f32,
+    /// Milliseconds a wandering entity keeps its direction before picking a new one.
+    pub random_direction_duration: f32,
+    /// Entities this many times bigger than us are threats.
+    pub predator_ratio: f32,
+    /// Entities this many times smaller than us are food.
+    pub prey_ratio: f32,
+    /// Energy gained per unit of plant energy (size squared) eaten.
+    pub plant_energy: f32,
+    /// An entity has children once its energy reaches this many milliseconds of its energy cost.
+    pub reproduction_threshold: f32,
+    /// Having a child costs this many milliseconds of the parent's energy cost.
+    pub reproduction_cost: f32,
+    /// A child's traits are its parent's scaled by a random factor in `1 ± mutation_amount`.
+    pub mutation_amount: f32,
+    /// Smallest value a heritable trait can mutate to.
+    pub min_trait: f32,
+    /// Biggest value a heritable trait can mutate to.
+    pub max_trait: f32,
+    /// Chance per tick that a plant seeds a new one, per unit of fertility.
+    pub plant_growth_rate: f32,
+    /// Most plants a terrain block can hold. Seeding slows down as a block fills up.
+    pub plant_carrying_capacity: u32,
+    /// Fertility a block loses every time a plant seeds in it.
+    pub plant_fertility_cost: f32,
+    /// Fertility a block gains when an entity dies on it, at once, or as its corpse rots when
+    /// it starved and corpses are on.
+    pub death_fertility: f32,
+    /// Energy the corpse of an entity that starved holds per unit of its body mass (size
+    /// squared), for scavengers to eat. 0 for no corpses. Entities that follow the rules seek
+    /// corpses out, neural brains don't sense them and only eat the ones they run into.
+    pub corpse_energy: f32,
+    /// Fraction of its energy and of its fertility a corpse loses every tick as it rots.
+    pub corpse_decay: f32,
+    /// Corpses with less energy than this have rotted away.
+    pub corpse_min_energy: f32,
+    /// Fraction of the way to `plant_max_size` a plant grows every tick on fully fertile land.
+    /// 0 keeps plants at their size at birth.
+    pub plant_growth_speed: f32,
+    /// Size plants grow towards.
+    pub plant_max_size: f32,
+    /// Ticks a plant lives unless it is eaten, 0 for forever.
+    pub plant_lifespan: u64,
+    /// Fertility a block gains when a plant dies of old age on it.
+    pub plant_death_fertility: f32,
+    /// Whether plants carry genomes: their max size, seed count, seed distance, growth speed
+    /// and toxicity are then heritable instead of set by the config.
+    pub evolving_plants: bool,
+    /// Extra heritable genes of plants. A gene named after a built-in plant gene (`max_size`,
+    /// `seed_count`, `seed_distance`, `growth_speed` or `toxicity`) replaces its default spec.
+    pub plant_genes: Vec<GeneSpec>,
+    /// Fraction of its growth speed a fully toxic plant loses, in proportion to its toxicity.
+    pub toxicity_growth_cost: f32,
+    /// Entities that follow the rules leave plants at least this toxic alone. Plants more than
+    /// half toxic take more energy than they give.
+    pub poisonous_toxicity: f32,
+    /// Fraction of the gap between the fertility of a block and its base fertility that closes
+    /// every tick.
+    pub fertility_regeneration: f32,
+    /// Fraction of the difference in fertility between two neighboring blocks that evens out
+    /// every tick, shared between the four neighbors.
+    pub fertility_diffusion: f32,
+    /// Random entities created with the world.
+    pub initial_entities: usize,
+    /// Random plants created with the world.
+    pub initial_plants: usize,
+    /// Extra heritable genes. A gene named after a built-in one (`size`, `sense_distance` or
+    /// `velocity_mag`) replaces the default spec built from `min_trait`, `max_trait` and
+    /// `mutation_amount`.
+    pub genes: Vec<GeneSpec>,
+    /// Whether entities carry a `diet` gene, from 0 for plants only to 1 for meat only, right
+    /// after the built-in genes. Without it every entity digests everything fully.
+    pub evolving_diet: bool,
+    /// How much specialists gain over generalists: an entity gets `(2 * preference) ^
+    /// diet_specialization` of the energy of what it eats, where the preference is `1 - diet`
+    /// for plants and `diet` for meat. 0 makes diet meaningless.
+    pub diet_specialization: f32,
+    /// Whether entities follow the built-in rules or steer with an evolved neural network.
+    pub behavior: Behavior,
+    /// Neurons in the hidden layer of neural brains.
+    pub brain_hidden_neurons: usize,
+    /// Standard deviation of the noise added to a brain weight when it mutates.
+    pub brain_mutation: f32,
+    /// Chance that a brain weight mutates when it is passed to a child.
+    pub brain_mutation_rate: f32,
+    /// Whether entities have children on their own or need a mate.
+    pub reproduction: Reproduction,
+    /// Sexual reproduction: farthest two entities can be from each other (between their edges)
+    /// to mate, in world units.
+    pub mate_range: f32,
+    /// Sexual reproduction: biggest `Genome::distance` between two entities that can mate.
+    pub mate_distance: f32,
+    /// Entities are grouped into species every this many ticks.
+    pub species_interval: u64,
+    /// Biggest `Genome::distance` between an entity and the center of its species.
+    pub species_distance: f32,
+    /// Ticks in a year of four seasons, 0 for no seasons.
+    pub year_length: u64,
+    /// What the plant growth rate is multiplied by in spring, summer, autumn and winter.
+    pub season_growth: [f32; 4],
+    /// What the fertility regeneration is multiplied by in spring, summer, autumn and winter.
+    pub season_regeneration: [f32; 4],
+    /// Ticks in a day, 0 for endless daylight.
+    pub day_length: u64,
+    /// What sense distances are multiplied by at midnight. Daylight brings it back to 1 at noon.
+    pub night_sense: f32,
+    /// How the terrain is laid out.
+    pub terrain: TerrainKind,
+    /// Noise terrain: the seed of the landscape. `None` uses the seed of the world, set it to
+    /// run several seeds on the same landscape.
+    pub terrain_seed: Option<u64>,
+    /// Noise terrain: size of the biggest hills and lakes, in blocks.
+    pub terrain_scale: f32,
+    /// Noise terrain: layers of noise, each adding finer detail.
+    pub terrain_octaves: u32,
+    /// Noise terrain: blocks lower than this elevation, in [0, 1], are water.
+    pub water_level: f32,
+}
+
+/// How entities have children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reproduction {
+    /// An entity with enough energy has a mutated copy of itself.
+    #[default]
+    Asexual,
+    /// Two compatible entities with enough energy close to each other share the cost of a child
+    /// whose genes are a mutated mix of theirs.
+    Sexual,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            sense_range: 100.0,
+            time_step: 1000.0 / 60.0,
+            max_speed: 0.1 / (1000.0 / 60.0),
+            random_direction_duration: 100.0 * (1000.0 / 60.0),
+            predator_ratio: 1.2,
+            prey_ratio: 0.8,
+            plant_energy: 5000.0,
+            reproduction_threshold: 10.0 * 1000.0,
+            reproduction_cost: 1000.0,
+            mutation_amount: 0.1,
+            min_trait: 0.1,
+            max_trait: 1.0,
+            plant_growth_rate: 0.01,
+            plant_carrying_capacity: 50 * 50,
+            plant_fertility_cost: 0.03,
+            death_fertility: 0.3,
+            corpse_energy: 0.0,
+            corpse_decay: 0.002,
+            corpse_min_energy: 1.0,
+            plant_growth_speed: 0.0,
+            plant_max_size: 0.4,
+            plant_lifespan: 0,
+            plant_death_fertility: 0.02,
+            evolving_plants: false,
+            plant_genes: vec![],
+            toxicity_growth_cost: 0.5,
+            poisonous_toxicity: 0.5,
+            fertility_regeneration: 0.0005,
+            fertility_diffusion: 0.02,
+            initial_entities: 0,
+            initial_plants: 0,
+            genes: vec![],
+            evolving_diet: false,
+            diet_specialization: 1.0,
+            behavior: Behavior::Rules,
+            brain_hidden_neurons: 6,
+            brain_mutation: 0.5,
+            brain_mutation_rate: 0.1,
+            reproduction: Reproduction::Asexual,
+            mate_range: 1.0,
+            mate_distance: 0.2,
+            species_interval: 60,
+            species_distance: 0.15,
+            year_length: 0,
+            season_growth: [1.25, 1.0, 0.75, 0.25],
+            season_regeneration: [1.5, 1.0, 0.75, 0.25],
+            day_length: 0,
+            night_sense: 0.4,
+            terrain: TerrainKind::Radial,
+            terrain_seed: None,
+            terrain_scale: 12.0,
+            terrain_octaves: 4,
+            water_level: 0.35,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The JSON could not be parsed into a config.
+    Parse(String),
+    /// A value is out of its valid range.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Parse(message) => write!(f, "invalid config: {}", message),
+            ConfigError::Invalid(message) => write!(f, "invalid config: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl SimConfig {
+    pub fn from_json(json: &str) -> Result<Self, ConfigError> {
+        let config: SimConfig =
+            serde_json::from_str(json).map_err(|error| ConfigError::Parse(error.to_string()))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a config can always be encoded")
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let non_negative = [
+            ("sense_range", self.sense_range),
+            ("max_speed", self.max_speed),
+            ("random_direction_duration", self.random_direction_duration),
+            ("plant_energy", self.plant_energy),
+            ("reproduction_cost", self.reproduction_cost),
+            ("mutation_amount", self.mutation_amount),
+            ("plant_growth_rate", self.plant_growth_rate),
+            ("plant_fertility_cost", self.plant_fertility_cost),
+            ("death_fertility", self.death_fertility),
+            ("corpse_energy", self.corpse_energy),
+            ("plant_death_fertility", self.plant_death_fertility),
+            ("diet_specialization", self.diet_specialization),
+            ("brain_mutation", self.brain_mutation),
+            ("mate_range", self.mate_range),
+            ("mate_distance", self.mate_distance),
+            ("species_distance", self.species_distance),
+        ];
+
+        for (name, value) in non_negative.iter() {
+            if !value.is_finite() || *value < 0.0 {
+                return Err(ConfigError::Invalid(format!(
+                    "{} must be a non negative number, got {}",
+                    name, value
+                )));
+            }
+        }
+
+        if !(self.time_step > 0.0 && self.time_step.is_finite()) {
+            return Err(ConfigError::Invalid(format!(
+                "time_step must be a positive number, got {}",
+                self.time_step
+            )));
+        }
+
+        if !(self.prey_ratio > 0.0 && self.prey_ratio <= 1.0) {
+            return Err(ConfigError::Invalid(format!(
+                "prey_ratio must be in (0, 1], got {}",
+                self.prey_ratio
+            )));
+        }
+
+        if !(self.predator_ratio >= 1.0 && self.predator_ratio.is_finite()) {
+            return Err(ConfigError::Invalid(format!(
+                "predator_ratio must be at least 1, got {}",
+                self.predator_ratio
+            )));
+        }
+
+        if !(self.reproduction_threshold.is_finite()
+            && self.reproduction_threshold > self.reproduction_cost)
+        {
+            return Err(ConfigError::Invalid(format!(
+                "reproduction_threshold ({}) must be bigger than reproduction_cost ({})",
+                self.reproduction_threshold, self.reproduction_cost
+            )));
+        }
+
+        if self.mutation_amount >= 1.0 {
+            return Err(ConfigError::Invalid(format!(
+                "mutation_amount must be below 1, got {}",
+                self.mutation_amount
+            )));
+        }
+
+        if !(self.min_trait > 0.0 && self.min_trait <= self.max_trait && self.max_trait <= 1.0) {
+            return Err(ConfigError::Invalid(format!(
+                "traits must satisfy 0 < min_trait <= max_trait <= 1, got {} and {}",
+                self.min_trait, self.max_trait
+            )));
+        }
+
+        if self.plant_carrying_capacity == 0 {
+            return Err(ConfigError::Invalid(
+                "plant_carrying_capacity must be at least 1".to_string(),
+            ));
+        }
+
+        if self.species_interval == 0 {
+            return Err(ConfigError::Invalid(
+                "species_interval must be at least 1".to_string(),
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.brain_mutation_rate) {
+            return Err(ConfigError::Invalid(format!(
+                "brain_mutation_rate must be in [0, 1], got {}",
+                self.brain_mutation_rate
+            )));
+        }
+
+        let fractions = [
+            ("fertility_regeneration", self.fertility_regeneration),
+            ("fertility_diffusion", self.fertility_diffusion),
+            ("plant_growth_speed", self.plant_growth_speed),
+            ("corpse_decay", self.corpse_decay),
+            ("toxicity_growth_cost", self.toxicity_growth_cost),
+            ("poisonous_toxicity", self.poisonous_toxicity),
+        ];
+
+        for (name, value) in fractions.iter() {
+            if !(0.0..=1.0).contains(value) {
+                return Err(ConfigError::Invalid(format!(
+                    "{} must be in [0, 1], got {}",
+                    name, value
+                )));
+            }
+        }
+
+        if !(self.plant_max_size > 0.0 && self.plant_max_size.is_finite()) {
+            return Err(ConfigError::Invalid(format!(
+                "plant_max_size must be a positive number, got {}",
+                self.plant_max_size
+            )));
+        }
+
+        // Corpses that never rot pile up forever.
+        if self.corpse_energy > 0.0 && self.corpse_decay <= 0.0 {
+            return Err(ConfigError::Invalid(
+                "corpse_decay must be positive when corpse_energy is".to_string(),
+            ));
+        }
+
+        if !(self.corpse_min_energy > 0.0 && self.corpse_min_energy.is_finite()) {
+            return Err(ConfigError::Invalid(format!(
+                "corpse_min_energy must be a positive number, got {}",
+                self.corpse_min_energy
+            )));
+        }
+
+        let seasons = [
+            ("season_growth", self.season_growth),
+            ("season_regeneration", self.season_regeneration),
+        ];
+
+        for (name, factors) in seasons.iter() {
+            if factors
+                .iter()
+                .any(|factor| !factor.is_finite() || *factor < 0.0)
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "{} must hold non negative numbers, got {:?}",
+                    name, factors
+                )));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.night_sense) {
+            return Err(ConfigError::Invalid(format!(
+                "night_sense must be in [0, 1], got {}",
+                self.night_sense
+            )));
+        }
+
+        if !(self.terrain_scale > 0.0 && self.terrain_scale.is_finite()) {
+            return Err(ConfigError::Invalid(format!(
+                "terrain_scale must be a positive number, got {}",
+                self.terrain_scale
+            )));
+        }
+
+        if !(1..=16).contains(&self.terrain_octaves) {
+            return Err(ConfigError::Invalid(format!(
+                "terrain_octaves must be in [1, 16], got {}",
+                self.terrain_octaves
+            )));
+        }
+
+        if !(0.0..=1.0).contains(&self.water_level) {
+            return Err(ConfigError::Invalid(format!(
+                "water_level must be in [0, 1], got {}",
+                self.water_level
+            )));
+        }
+
+        if self.behavior == Behavior::Neural && self.brain_hidden_neurons == 0 {
+            return Err(ConfigError::Invalid(
+                "brain_hidden_neurons must be at least 1".to_string(),
+            ));
+        }
+
+        validate_genes(&self.genes)?;
+        for gene in self.genes.iter() {
+            // Colors, histograms and the cost of living assume the built-in traits are in (0, 1].
+            if BUILT_IN_GENES.contains(&gene.name.as_str())
+                && !(gene.kind == GeneKind::Real && gene.min > 0.0 && gene.max <= 1.0)
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "gene {}: built-in genes must be real numbers in (0, 1]",
+                    gene.name
+                )));
+            }
+
+            if self.evolving_diet && gene.name == "diet" && !(gene.min >= 0.0 && gene.max <= 1.0) {
+                return Err(ConfigError::Invalid(
+                    "gene diet: must be in [0, 1]".to_string(),
+                ));
+            }
+        }
+
+        validate_genes(&self.plant_genes)?;
+        for gene in self.plant_genes.iter() {
+            let valid = match gene.name.as_str() {
+                "max_size" => gene.min > 0.0,
+                "seed_count" | "seed_distance" => gene.min >= 0.0,
+                "growth_speed" | "toxicity" => gene.min >= 0.0 && gene.max <= 1.0,
+                _ => true,
+            };
+            if !valid {
+                return Err(ConfigError::Invalid(format!(
+                    "gene {}: out of the range of the plant trait",
+                    gene.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_genes(genes: &[GeneSpec]) -> Result<(), ConfigError> {
+    for (i, gene) in genes.iter().enumerate() {
+        validate_gene(gene)?;
+
+        if genes[..i].iter().any(|other| other.name == gene.name) {
+            return Err(ConfigError::Invalid(format!(
+                "gene {} is defined twice",
+                gene.name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_gene(gene: &GeneSpec) -> Result<(), ConfigError> {
+    let invalid = |message: String| {
+        Err(ConfigError::Invalid(format!(
+            "gene {}: {}",
+            gene.name, message
+        )))
+    };
+
+    if !(gene.min.is_finite() && gene.max.is_finite() && gene.min <= gene.max) {
+        return invalid(format!(
+            "min ({}) must not be bigger than max ({})",
+            gene.min, gene.max
+        ));
+    }
+
+    if gene.kind == GeneKind::Integer && gene.min.ceil() > gene.max.floor() {
+        return invalid("the range must contain a whole number".to_string());
+    }
+
+    if !(0.0..=1.0).contains(&gene.rate) {
+        return invalid(format!("rate must be in [0, 1], got {}", gene.rate));
+    }
+
+    let spread = match gene.mutation {
+        Mutation::Relative { amount } | Mutation::Uniform { amount } => amount,
+        Mutation::Gaussian { sigma } => sigma,
+    };
+    if !(spread.is_finite() && spread >= 0.0) {
+        return invalid(format!(
+            "the mutation must be a non negative number, got {}",
+            spread
+        ));
+    }
+
+    Ok(())
+}
@@ -1,636 +1,828 @@
-use glam::{Vec2, Vec3};
-use wasm_bindgen::prelude::*;
-
-#[path = "utils.rs"]
-mod utils;
-
-#[wasm_bindgen(module = "/js-side/src/Renderer.ts")]
-extern "C" {
-    type Renderer;
-    // Import the constructor for the Renderer class.
-    #[wasm_bindgen(constructor)]
-    fn new() -> Renderer;
-    #[wasm_bindgen(method)]
-    fn circle(this: &Renderer, x: f32, y: f32, radius: f32, color: &str);
-    #[wasm_bindgen(method)]
-    fn rect(this: &Renderer, x: f32, y: f32, width: f32, height: f32, color: &str);
-    #[wasm_bindgen(method)]
-    fn clear(this: &Renderer);
-    #[wasm_bindgen(method, js_name = moveCamera)]
-    fn move_camera(this: &Renderer, x: f32, y: f32);
-    #[wasm_bindgen(method, js_name = zoomCamera)]
-    fn zoom(this: &Renderer, zoom: f32, x: f32, y: f32);
-    #[wasm_bindgen(method, js_name = getScale)]
-    fn get_scale(this: &Renderer) -> f32;
-    #[wasm_bindgen(method, js_name = getCameraX)]
-    fn get_camera_x(this: &Renderer) -> f32;
-    #[wasm_bindgen(method, js_name = getCameraY)]
-    fn get_camera_y(this: &Renderer) -> f32;
-}
-
-#[wasm_bindgen(module = "/js-side/src/Input.ts")]
-extern "C" {
-    type Input;
-    #[wasm_bindgen(static_method_of = Input)]
-    fn init();
-    #[wasm_bindgen(static_method_of = Input, js_name = isDown)]
-    fn is_down(key: &str) -> bool;
-    #[wasm_bindgen(static_method_of = Input, js_name = isMouseDown)]
-    fn is_mouse_down() -> bool;
-    #[wasm_bindgen(static_method_of = Input, js_name = getWheel)]
-    fn get_wheel() -> f32;
-    #[wasm_bindgen(static_method_of = Input, js_name = getMouseX)]
-    fn get_mouse_x() -> f32;
-    #[wasm_bindgen(static_method_of = Input, js_name = getMouseY)]
-    fn get_mouse_y() -> f32;
-}
-
-struct TerrainBlock {
-    number_of_plants: u32,
-    max_carrying_capacity: u32,
-    fertility: f32,
+use glam::Vec2;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::brain::{think, Behavior, Decision, Senses};
+use crate::config::{Reproduction, SimConfig};
+use crate::genome::{
+    Genome, GenomeLayout, DIET, GROWTH_SPEED, PLANT_MAX_SIZE, SEED_COUNT, SEED_DISTANCE,
+    SENSE_DISTANCE, SIZE, TOXICITY, VELOCITY_MAG,
+};
+use crate::id::Id;
+use crate::spatial::SpatialGrid;
+use crate::species::SpeciesId;
+
+// Side of a cell of the grids used to find the closest entity and plant.
+static SPATIAL_CELL_SIZE: f32 = 5.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plant {
+    #[serde(default)]
+    pub(crate) id: Id,
+    position: Vec2,
+    size: f32,
+    // Ticks since the plant sprouted.
+    #[serde(default)]
+    pub(crate) age: u64,
+    // Empty unless plants evolve, see `GenomeLayout::for_plants`.
+    #[serde(default)]
+    genome: Genome,
 }
 
-// Create a color RGB(200, 181, 170) this is for not fertile terrain
-static INFERTILE_TERRAIN_COLOR: Vec3 = Vec3::new(200.0, 181.0, 170.0);
-static FERTILE_TERRAIN_COLOR: Vec3 = Vec3::new(58.0, 46.0, 39.0);
-
-struct Terrain {
-    blocks: Vec<Vec<TerrainBlock>>,
-}
+impl Plant {
+    pub fn new(position: Vec2, size: f32) -> Self {
+        Self::with_genome(Genome::default(), position, size)
+    }
 
-static GRID_SIZE: i32 = 50;
-static HALF_GRID_SIZE: i32 = GRID_SIZE / 2;
-static BLOCK_SIZE: f32 = 200.0;
+    pub fn with_genome(genome: Genome, position: Vec2, size: f32) -> Self {
+        Self {
+            id: Id::UNASSIGNED,
+            position,
+            size,
+            age: 0,
+            genome,
+        }
+    }
 
-impl Terrain {
-    fn new() -> Self {
-        let mut blocks: Vec<Vec<TerrainBlock>> = vec![];
+    /// Unassigned until the plant is added to a `Simulation`.
+    pub fn id(&self) -> Id {
+        self.id
+    }
 
-        let max_distance_from_center =
-            Vec2::new((HALF_GRID_SIZE) as f32, (HALF_GRID_SIZE) as f32).length();
+    pub fn position(&self) -> Vec2 {
+        self.position
+    }
 
-        for x in (-HALF_GRID_SIZE)..HALF_GRID_SIZE {
-            let mut row: Vec<TerrainBlock> = vec![];
+    pub fn size(&self) -> f32 {
+        self.size
+    }
 
-            for y in (-HALF_GRID_SIZE)..HALF_GRID_SIZE {
-                // Places closer to the center are more fertile
+    /// Ticks since the plant sprouted.
+    pub fn age(&self) -> u64 {
+        self.age
+    }
 
-                let distance_from_center = Vec2::new(x as f32, y as f32).length();
-                let fertility = 1.0 - (distance_from_center / max_distance_from_center);
+    pub fn genome(&self) -> &Genome {
+        &self.genome
+    }
 
-                row.push(TerrainBlock {
-                    number_of_plants: 0,
-                    fertility,
-                    max_carrying_capacity: 50 * 50,
-                });
-            }
+    pub(crate) fn genome_mut(&mut self) -> &mut Genome {
+        &mut self.genome
+    }
 
-            blocks.push(row);
-        }
+    /// The size the plant grows towards: its `max_size` gene, or `SimConfig::plant_max_size`
+    /// when plants don't evolve.
+    pub fn max_size(&self, config: &SimConfig) -> f32 {
+        self.trait_or(PLANT_MAX_SIZE, config.plant_max_size)
+    }
 
-        Self { blocks }
+    /// Seeds the plant spreads every time it seeds.
+    pub fn seed_count(&self) -> u32 {
+        self.trait_or(SEED_COUNT, 1.0) as u32
     }
-}
 
-#[wasm_bindgen]
-pub struct App {
-    entities: Vec<Entity>,
-    renderer: Renderer,
-    plants: Vec<Plant>,
-    terrain: Terrain,
-}
+    /// How far seeds land, in sizes of the plant and the seed.
+    pub fn seed_distance(&self) -> f32 {
+        self.trait_or(SEED_DISTANCE, 10.0)
+    }
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
-}
+    /// See `SimConfig::plant_growth_speed`.
+    pub fn growth_speed(&self, config: &SimConfig) -> f32 {
+        self.trait_or(GROWTH_SPEED, config.plant_growth_speed)
+    }
 
-#[wasm_bindgen]
-impl App {
-    pub fn new() -> Self {
-        utils::set_panic_hook();
-        let renderer = Renderer::new();
-        Input::init();
-
-        let mut entities: Vec<Entity> = vec![];
-
-        // for _ in 0..100 {
-        //     entities.push(Entity {
-        //         position: Vec2::new(
-        //             (rand::random::<f32>() * 2.0 - 1.0) * 100.0,
-        //             (rand::random::<f32>() * 2.0 - 1.0) * 100.0,
-        //         ),
-        //         size: rand::random::<f32>() * 0.9 + 0.1,
-        //         sense_distance: rand::random::<f32>() * 0.9 + 0.1,
-        //         velocity_mag: rand::random::<f32>() * 0.9 + 0.1,
-        //         random_direction: Vec2::new(
-        //             rand::random::<f32>() * 2.0 - 1.0,
-        //             rand::random::<f32>() * 2.0 - 1.0,
-        //         )
-        //         .normalize(),
-        //         random_direction_uses: 0,
-        //         energy: 10000.0,
-        //     });
-        // }
-
-        let mut plants: Vec<Plant> = vec![];
-
-        // for _ in 0..1000 {
-        //     plants.push(Plant {
-        //         position: Vec2::new(
-        //             (rand::random::<f32>() * 2.0 - 1.0) * 100.0,
-        //             (rand::random::<f32>() * 2.0 - 1.0) * 100.0,
-        //         ),
-        //         size: rand::random::<f32>() * 0.1 + 0.1,
-        //     });
-        // }
-
-        let terrain = Terrain::new();
+    /// From 0 to 1. Eating a plant gives `1 - 2 * toxicity` of its energy, so plants more than
+    /// half toxic are poisonous. Toxic plants grow slower.
+    pub fn toxicity(&self) -> f32 {
+        self.trait_or(TOXICITY, 0.0)
+    }
 
-        Self {
-            entities,
-            renderer,
-            plants,
-            terrain,
-        }
+    // The built-in plant genes come first in every plant genome that has them.
+    fn trait_or(&self, index: usize, default: f32) -> f32 {
+        self.genome.values().get(index).copied().unwrap_or(default)
     }
 
-    pub fn update(&mut self, dt: f32) {
-        // Clear the screen
-        self.renderer.clear();
-
-        // Draw terrain
-        for (x, row) in self.terrain.blocks.iter().enumerate() {
-            for (y, block) in row.iter().enumerate() {
-                let fertility = block.fertility;
-
-                let color = linear_interpolate_vec(
-                    INFERTILE_TERRAIN_COLOR,
-                    FERTILE_TERRAIN_COLOR,
-                    fertility,
-                );
-                let x = x as f32 - HALF_GRID_SIZE as f32;
-                let y = y as f32 - HALF_GRID_SIZE as f32;
-
-                self.renderer.rect(
-                    x * BLOCK_SIZE,
-                    y * BLOCK_SIZE,
-                    BLOCK_SIZE,
-                    BLOCK_SIZE,
-                    &format!(
-                        "rgb({}, {}, {})",
-                        color.x as u8, color.y as u8, color.z as u8
-                    ),
-                );
-            }
-        }
+    pub fn energy(&self) -> f32 {
+        self.size * self.size
+    }
 
-        update(&mut self.entities, &mut self.plants, dt);
+    /// Ages the plant by a tick and grows it towards its `max_size`, faster on more `fertility`
+    /// and in seasons with a bigger `growth_factor`. Returns whether it died of old age, which
+    /// leaves it with a size of 0 like an eaten plant.
+    pub(crate) fn live(&mut self, fertility: f32, growth_factor: f32, config: &SimConfig) -> bool {
+        self.age += 1;
 
-        // Remove dead entities and increase the fertility of the land where they died.
-        let mut dead_entities: Vec<usize> = Vec::new();
-        for i in 0..self.entities.len() {
-            if self.entities[i].energy <= 0.0 {
-                dead_entities.push(i);
-            }
+        if config.plant_lifespan > 0 && self.age >= config.plant_lifespan {
+            self.size = 0.0;
+            return true;
         }
 
-        // Increase the fertility of the land where they died.
-        for i in 0..dead_entities.len() {
-            let entity = &self.entities[dead_entities[i]];
-
-            if entity.position.x.is_nan() || entity.position.y.is_nan() {
-                continue;
-            }
-
-            // If the plant is in a fertile area the probability of it growing is higher
-            let block_x = ((entity.position.x / 4.0) + HALF_GRID_SIZE as f32).round() as usize;
-            let block_y = ((entity.position.y / 4.0) + HALF_GRID_SIZE as f32).round() as usize;
+        let room = (self.max_size(config) - self.size).max(0.0);
+        let speed =
+            self.growth_speed(config) * (1.0 - config.toxicity_growth_cost * self.toxicity());
+        self.size += speed * fertility * growth_factor * room;
 
-            if block_x >= (GRID_SIZE as usize) || block_y >= (GRID_SIZE as usize) {
-                continue;
-            }
+        false
+    }
+}
 
-            self.terrain.blocks[block_x][block_y].fertility += 0.3;
-        }
+/// What is left of an entity that starved: food for scavengers until it rots into the land.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Corpse {
+    position: Vec2,
+    size: f32,
+    energy: f32,
+    // What the land under it gains as it rots.
+    fertility: f32,
+}
 
-        self.plants.retain(|plant| plant.size > 0.0);
-        self.entities.retain(|entity| entity.energy > 0.0);
-
-        // Draw entities
-        for entity in self.entities.iter() {
-            self.renderer.circle(
-                entity.position.x * 50.0,
-                entity.position.y * 50.0,
-                entity.size * 50.0,
-                &format!(
-                    "rgb({}, {}, {})",
-                    (entity.size * 255.0) as u8,
-                    (entity.sense_distance * 255.0) as u8,
-                    (entity.velocity_mag * 255.0) as u8
-                ),
-            );
+impl Corpse {
+    /// The corpse `entity` leaves, holding `SimConfig::corpse_energy` per unit of its body mass
+    /// and `SimConfig::death_fertility`.
+    pub fn new(entity: &Entity, config: &SimConfig) -> Self {
+        Self {
+            position: entity.position,
+            size: entity.size(),
+            energy: config.corpse_energy * entity.size() * entity.size(),
+            fertility: config.death_fertility,
         }
+    }
 
-        // Generate more plants
-        // Plants are more likely to grow in areas near the (0, 0) point
-
-        let mut new_plants: Vec<Plant> = vec![];
+    pub fn position(&self) -> Vec2 {
+        self.position
+    }
 
-        for plant in self.plants.iter() {
-            // If the plant is in a fertile area the probability of it growing is higher
-            let block_x = ((plant.position.x / 4.0) + HALF_GRID_SIZE as f32).round() as usize;
-            let block_y = ((plant.position.y / 4.0) + HALF_GRID_SIZE as f32).round() as usize;
+    pub fn size(&self) -> f32 {
+        self.size
+    }
 
-            if block_x >= (GRID_SIZE as usize) || block_y >= (GRID_SIZE as usize) {
-                continue;
-            }
+    /// What a scavenger gains by eating it.
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
 
-            let block = &self.terrain.blocks[block_x][block_y];
+    /// Fertility the corpse has yet to give to the land.
+    pub fn fertility(&self) -> f32 {
+        self.fertility
+    }
 
-            let probability_of_growth = block.fertility * 0.01;
+    /// Whether it has been eaten or has rotted away.
+    pub fn is_gone(&self, config: &SimConfig) -> bool {
+        self.energy < config.corpse_min_energy
+    }
 
-            if rand::random::<f32>() < probability_of_growth {
-                let new_plant_size = rand::random::<f32>() * 0.1 + 0.1;
-                let random_vec = Vec2::new(
-                    rand::random::<f32>() * 2.0 - 1.0,
-                    rand::random::<f32>() * 2.0 - 1.0,
-                )
-                .normalize();
+    /// Rots for a tick: loses `SimConfig::corpse_decay` of its energy and of its fertility.
+    /// Returns the fertility released, all that is left once the corpse is gone.
+    pub(crate) fn decay(&mut self, config: &SimConfig) -> f32 {
+        self.energy -= self.energy * config.corpse_decay;
 
-                let new_plant_position =
-                    plant.position + random_vec * (plant.size + new_plant_size) * 10.0;
+        let released = if self.is_gone(config) {
+            self.fertility
+        } else {
+            self.fertility * config.corpse_decay
+        };
+        self.fertility -= released;
 
-                new_plants.push(Plant {
-                    position: new_plant_position,
-                    size: new_plant_size,
-                });
+        released
+    }
+}
 
-                // Reduce fertility of the block
-                self.terrain.blocks[block_x][block_y].fertility -= 0.03;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entity {
+    #[serde(default)]
+    pub(crate) id: Id,
+    #[serde(default)]
+    parent_id: Option<Id>,
+    // The mate of the parent, for children of sexual reproduction.
+    #[serde(default)]
+    other_parent_id: Option<Id>,
+    // Inherited from the parent, then updated by every clustering.
+    #[serde(default)]
+    pub(crate) species: Option<SpeciesId>,
+
+    // Passed from the parent to the child when the child is born, slightly mutated.
+    // The built-in genes (size, sense distance and velocity) are numbers between 0 and 1 that
+    // we also use to create a color for the entity.
+    genome: Genome,
 
-                if self.terrain.blocks[block_x][block_y].fertility < 0.0 {
-                    self.terrain.blocks[block_x][block_y].fertility = 0.0;
-                }
-            }
-        }
+    random_direction: Vec2,
+    // Milliseconds spent wandering in `random_direction`.
+    #[serde(default)]
+    random_direction_age: f32,
 
-        self.plants.append(&mut new_plants);
-
-        // Draw plants
-        for plant in self.plants.iter() {
-            self.renderer.rect(
-                plant.position.x * 50.0,
-                plant.position.y * 50.0,
-                plant.size * 50.0,
-                plant.size * 50.0,
-                "#004e00",
-            );
-        }
+    position: Vec2,
+    // World units per millisecond.
+    #[serde(default)]
+    velocity: Vec2,
+    pub energy: f32,
+    // Whether it died in the jaws of another entity this step, rather than of hunger.
+    #[serde(skip)]
+    pub(crate) eaten: bool,
+}
 
-        // Add WASD movement
-        let mut movement = Vec2::new(0.0, 0.0);
-        if Input::is_down("w") {
-            movement.y += 15.0;
-        }
-        if Input::is_down("s") {
-            movement.y -= 15.0;
-        }
-        if Input::is_down("a") {
-            movement.x += 15.0;
-        }
-        if Input::is_down("d") {
-            movement.x -= 15.0;
+impl Entity {
+    pub fn new(genome: Genome, position: Vec2, energy: f32, rng: &mut impl Rng) -> Self {
+        Self {
+            id: Id::UNASSIGNED,
+            parent_id: None,
+            other_parent_id: None,
+            species: None,
+            genome,
+            position,
+            energy,
+            random_direction: random_direction(rng),
+            random_direction_age: 0.0,
+            velocity: Vec2::ZERO,
+            eaten: false,
         }
-        self.renderer.move_camera(movement.x, movement.y);
-
-        if Input::is_mouse_down() {
-            // Add a new plant
-            let mouse_x = Input::get_mouse_x();
-            let mouse_y = Input::get_mouse_y();
+    }
 
-            let camera_x = self.renderer.get_camera_x();
-            let camera_y = self.renderer.get_camera_y();
+    /// Unassigned until the entity is added to a `Simulation`.
+    pub fn id(&self) -> Id {
+        self.id
+    }
 
-            let scale = self.renderer.get_scale();
+    /// The id of the entity this one was born from, `None` for the first generation.
+    pub fn parent_id(&self) -> Option<Id> {
+        self.parent_id
+    }
 
-            let new_plant = Plant {
-                position: Vec2::new(
-                    ((mouse_x - camera_x) / 50.0) / scale,
-                    ((mouse_y - camera_y) / 50.0) / scale,
-                ),
-                size: 0.1,
-            };
+    /// The id of the mate of the parent, `None` unless the entity was born from two parents.
+    pub fn other_parent_id(&self) -> Option<Id> {
+        self.other_parent_id
+    }
 
-            self.plants.push(new_plant);
-        }
+    /// `None` until the first clustering after the entity was added to the world.
+    pub fn species(&self) -> Option<SpeciesId> {
+        self.species
+    }
 
-        // Zoom
-        let wheel = Input::get_wheel();
+    pub fn genome(&self) -> &Genome {
+        &self.genome
+    }
 
-        if wheel > 0.0 {
-            self.renderer
-                .zoom(0.9, Input::get_mouse_x(), Input::get_mouse_y());
-        } else if wheel < 0.0 {
-            self.renderer
-                .zoom(1.1, Input::get_mouse_x(), Input::get_mouse_y());
-        }
+    pub(crate) fn genome_mut(&mut self) -> &mut Genome {
+        &mut self.genome
+    }
 
-        // for _ in 0..10 {
-        //     self.plants.push(Plant {
-        //         position: Vec2::new(
-        //             (rand::random::<f32>() * 2.0 - 1.0) * 100.0,
-        //             (rand::random::<f32>() * 2.0 - 1.0) * 100.0,
-        //         ),
-        //         size: rand::random::<f32>() * 0.1 + 0.1,
-        //     });
-        // }
+    pub fn size(&self) -> f32 {
+        self.genome.get(SIZE)
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-pub struct Plant {
-    position: Vec2,
-    size: f32,
-}
+    pub fn sense_distance(&self) -> f32 {
+        self.genome.get(SENSE_DISTANCE)
+    }
 
-impl Plant {
-    pub fn energy(&self) -> f32 {
-        self.size * self.size
+    pub fn velocity_mag(&self) -> f32 {
+        self.genome.get(VELOCITY_MAG)
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-pub struct Entity {
-    // These properties are passed from the parent to the child when the child is born.
-    // The child will slightly mutate these properties.
-    // We use these three properties to create a color for the entity.
-    // Each one of these properties is a number between 0 and 1.
-    size: f32,
-    sense_distance: f32,
-    velocity_mag: f32,
+    pub fn position(&self) -> Vec2 {
+        self.position
+    }
 
-    random_direction: Vec2,
-    random_direction_uses: u32,
+    /// Current velocity, in world units per millisecond.
+    pub fn velocity(&self) -> Vec2 {
+        self.velocity
+    }
 
-    position: Vec2,
-    pub energy: f32,
-}
+    /// Fastest the entity can move, in world units per millisecond.
+    pub fn max_speed(&self, config: &SimConfig) -> f32 {
+        self.velocity_mag() * config.max_speed
+    }
 
-impl Entity {
-    pub fn new(
-        size: f32,
-        sense_distance: f32,
-        velocity_mag: f32,
-        position: Vec2,
-        energy: f32,
-    ) -> Self {
-        Self {
-            size,
-            sense_distance,
-            position,
-            velocity_mag,
-            energy,
-            random_direction: Vec2::new(
-                rand::random::<f32>() * 2.0 - 1.0,
-                rand::random::<f32>() * 2.0 - 1.0,
-            )
-            .normalize(),
-            random_direction_uses: 0,
+    /// From 0 for plants only to 1 for meat only. 0.5 when diet doesn't evolve.
+    pub fn diet(&self, config: &SimConfig) -> f32 {
+        if config.evolving_diet {
+            self.genome.get(DIET)
+        } else {
+            0.5
         }
     }
 
-    fn mass(&self) -> f32 {
-        self.size * self.size
+    /// Fraction of the energy of plants the entity gets out of them.
+    pub fn plant_efficiency(&self, config: &SimConfig) -> f32 {
+        digestion(1.0 - self.diet(config), config)
     }
 
-    fn velocity(&self) -> f32 {
-        (self.velocity_mag) / 10.0
+    /// Fraction of the energy of prey and corpses the entity gets out of them.
+    pub fn meat_efficiency(&self, config: &SimConfig) -> f32 {
+        digestion(self.diet(config), config)
     }
 
-    fn eat(&mut self, other: &mut Entity) {
-        self.energy = self.energy + other.energy * self.size;
+    fn eat(&mut self, other: &mut Entity, config: &SimConfig) {
+        self.energy += other.energy * self.size() * self.meat_efficiency(config);
         other.energy = 0.0;
+        other.eaten = true;
     }
 
-    fn eat_plant(&mut self, plant: &mut Plant) {
-        self.energy = self.energy + plant.energy() * 5000.0;
+    /// Whether the entity died eaten by another one, rather than of hunger. Only meaningful
+    /// during the step it died in.
+    pub fn eaten(&self) -> bool {
+        self.eaten
+    }
+
+    fn eat_corpse(&mut self, corpse: &mut Corpse, config: &SimConfig) {
+        self.energy += corpse.energy * self.meat_efficiency(config);
+        corpse.energy = 0.0;
+        corpse.fertility = 0.0;
+    }
+
+    fn eat_plant(&mut self, plant: &mut Plant, config: &SimConfig) {
+        self.energy += plant.energy()
+            * config.plant_energy
+            * (1.0 - 2.0 * plant.toxicity())
+            * self.plant_efficiency(config);
         plant.size = 0.0;
     }
 
+    fn can_reproduce(&self, config: &SimConfig) -> bool {
+        self.energy >= self.energy_cost() * config.reproduction_threshold
+    }
+
     fn energy_cost(&self) -> f32 {
         // Energy cost to be alive = mass * velocity^2 + sense_distancew
-        self.size * self.velocity_mag + self.sense_distance
+        self.size() * self.velocity_mag() + self.sense_distance()
     }
 
-    fn move_randomly(&mut self, dt: f32) {
-        if self.random_direction_uses > 100 {
-            self.random_direction = Vec2::new(
-                rand::random::<f32>() * 2.0 - 1.0,
-                rand::random::<f32>() * 2.0 - 1.0,
-            )
-            .normalize();
-            self.random_direction_uses = 0;
+    /// Runs at full speed in `direction` (a unit vector) for `dt` milliseconds.
+    fn run(&mut self, direction: Vec2, dt: f32, config: &SimConfig) {
+        self.velocity = direction * self.max_speed(config);
+        self.position += self.velocity * dt;
+    }
+
+    fn move_randomly(&mut self, dt: f32, config: &SimConfig, rng: &mut impl Rng) {
+        if self.random_direction_age > config.random_direction_duration {
+            self.random_direction = random_direction(rng);
+            self.random_direction_age = 0.0;
         }
 
-        self.position = self.position + self.random_direction * self.velocity();
-        self.random_direction_uses += 1;
+        self.run(self.random_direction, dt, config);
+        self.random_direction_age += dt;
         self.consume_energy(dt);
     }
 
-    fn move_towards(&mut self, target: Vec2, distance: f32, dt: f32) {
-        // If the distance is less than the velocity then we should just move to the target.
-        if distance + self.size < self.velocity() {
+    fn move_towards(&mut self, target: Vec2, distance: f32, dt: f32, config: &SimConfig) {
+        // If we would overshoot the target during this step then we should just move to it.
+        if distance + self.size() < self.max_speed(config) * dt {
+            self.velocity = if dt > 0.0 {
+                (target - self.position) / dt
+            } else {
+                Vec2::ZERO
+            };
             self.position = target;
         } else {
             let direction = (target - self.position).normalize();
-            self.position = self.position + direction * self.velocity();
+            self.run(direction, dt, config);
         }
 
         self.consume_energy(dt);
     }
 
-    fn move_away(&mut self, target: Vec2, dt: f32) {
+    fn move_away(&mut self, target: Vec2, dt: f32, config: &SimConfig) {
         let direction = (target - self.position).normalize();
-        self.position = self.position - direction * self.velocity();
+        self.run(-direction, dt, config);
+        self.consume_energy(dt);
+    }
+
+    /// Moves where a brain decided for `dt` milliseconds.
+    fn steer(&mut self, decision: Decision, dt: f32, config: &SimConfig) {
+        let direction = decision.steering.normalize_or_zero();
+        self.velocity = direction * self.max_speed(config) * decision.speed;
+        self.position += self.velocity * dt;
         self.consume_energy(dt);
     }
 
     fn consume_energy(&mut self, dt: f32) {
-        self.energy = self.energy - self.energy_cost() * dt;
+        self.energy -= self.energy_cost() * dt;
+    }
+}
+
+pub fn add_children(
+    entities: &mut Vec<Entity>,
+    layout: &GenomeLayout,
+    config: &SimConfig,
+    rng: &mut impl Rng,
+) {
+    match config.reproduction {
+        Reproduction::Asexual => reproduce_alone(entities, layout, config, rng),
+        Reproduction::Sexual => mate(entities, layout, config, rng),
     }
 }
 
-pub fn add_children(entities: &mut Vec<Entity>) {
+fn reproduce_alone(
+    entities: &mut Vec<Entity>,
+    layout: &GenomeLayout,
+    config: &SimConfig,
+    rng: &mut impl Rng,
+) {
     let mut new_entities: Vec<Entity> = Vec::new();
     for entity in entities.iter_mut() {
         // If we have enough energy to have children then we should have children.
-        if entity.energy >= entity.energy_cost() * 10.0 * 1000.0 {
-            let reproduction_cost = entity.energy_cost() * 1000.0; // Equivalent to 1 second of energy.
-                                                                   // Remove half of the energy from the parent and remove the energy cost of reproduction.
+        if entity.can_reproduce(config) {
+            // Equivalent to 1 second of energy by default.
+            let reproduction_cost = entity.energy_cost() * config.reproduction_cost;
+            // Remove half of the energy from the parent and remove the energy cost of reproduction.
             entity.energy = (entity.energy - reproduction_cost) / 2.0;
             let child_energy = entity.energy;
 
             // Move the child next to the parent.
-            let random_vec = Vec2::new(
-                rand::random::<f32>() * 2.0 - 1.0,
-                rand::random::<f32>() * 2.0 - 1.0,
-            )
-            .normalize();
-
-            let child_size = randomize_trait(entity.size);
-            let child_position = entity.position + random_vec * (entity.size + child_size);
-
-            new_entities.push(Entity::new(
-                child_size,
-                randomize_trait(entity.sense_distance),
-                randomize_trait(entity.velocity_mag),
-                child_position,
-                child_energy,
-            ));
+            let random_vec = random_direction(rng);
+
+            let genome = entity.genome.mutated(layout, rng);
+            let child_position = entity.position + random_vec * (entity.size() + genome.get(SIZE));
+
+            let mut child = Entity::new(genome, child_position, child_energy, rng);
+            child.parent_id = Some(entity.id);
+            child.species = entity.species;
+
+            new_entities.push(child);
         }
     }
 
     entities.append(&mut new_entities);
 }
 
-fn randomize_trait(mut trait_value: f32) -> f32 {
-    trait_value = trait_value * (1.0 + rand::random::<f32>() * 0.2 - 0.1); // +/- 10%
+// Every entity that can reproduce mates with the closest compatible entity within reach that
+// can reproduce too and hasn't mated during this step. Entities without a mate wait.
+fn mate(entities: &mut Vec<Entity>, layout: &GenomeLayout, config: &SimConfig, rng: &mut impl Rng) {
+    let mut grid = SpatialGrid::new(SPATIAL_CELL_SIZE);
+    for (i, entity) in entities.iter().enumerate() {
+        if entity.can_reproduce(config) {
+            grid.insert(i, entity.position, entity.size());
+        }
+    }
+
+    let mut mated = vec![false; entities.len()];
+    let mut new_entities: Vec<Entity> = Vec::new();
 
-    if trait_value < 0.1 {
-        0.1
-    } else if trait_value > 1.0 {
-        1.0
-    } else {
-        trait_value
+    for i in 0..entities.len() {
+        let entity = &entities[i];
+        if mated[i] || !entity.can_reproduce(config) {
+            continue;
+        }
+
+        let closest = grid.closest(entity.position, entity.size(), config.mate_range, |j| {
+            let other_entity = &entities[j];
+            if i == j
+                || mated[j]
+                || entity.genome.distance(&other_entity.genome, layout) > config.mate_distance
+            {
+                return None;
+            }
+
+            Some(
+                other_entity.position.distance(entity.position)
+                    - (other_entity.size() + entity.size()),
+            )
+        });
+        let j = match closest {
+            Some((j, _)) => j,
+            None => continue,
+        };
+        mated[i] = true;
+        mated[j] = true;
+
+        let (entity, partner) = pair_mut(entities, i, j);
+
+        // The parents split the cost, then each gives half of what it has left to the child.
+        let reproduction_cost =
+            (entity.energy_cost() + partner.energy_cost()) / 2.0 * config.reproduction_cost;
+        entity.energy = (entity.energy - reproduction_cost / 2.0) / 2.0;
+        partner.energy = (partner.energy - reproduction_cost / 2.0) / 2.0;
+        let child_energy = entity.energy + partner.energy;
+
+        // Move the child next to the parent.
+        let random_vec = random_direction(rng);
+
+        let genome = entity
+            .genome
+            .crossover(&partner.genome, rng)
+            .mutated(layout, rng);
+        let child_position = entity.position + random_vec * (entity.size() + genome.get(SIZE));
+
+        let mut child = Entity::new(genome, child_position, child_energy, rng);
+        child.parent_id = Some(entity.id);
+        child.other_parent_id = Some(partner.id);
+        child.species = entity.species;
+
+        new_entities.push(child);
     }
+
+    entities.append(&mut new_entities);
 }
 
-pub fn update(entities: &mut Vec<Entity>, plants: &mut Vec<Plant>, dt: f32) {
-    // An entity does not interact with entities that are roughly the same size as it
-    // because they are not a threat and they are not food.
+/// Moves, feeds and breeds every entity for one step. Sense distances are multiplied by
+/// `sense_factor`, see `Clock::sense_factor`.
+pub fn update(
+    entities: &mut Vec<Entity>,
+    food: Food,
+    layout: &GenomeLayout,
+    config: &SimConfig,
+    sense_factor: f32,
+    dt: f32,
+    rng: &mut impl Rng,
+) {
+    let Food { plants, corpses } = food;
+
+    let mut entity_grid = SpatialGrid::new(SPATIAL_CELL_SIZE);
+    for (i, entity) in entities.iter().enumerate() {
+        entity_grid.insert(i, entity.position, entity.size());
+    }
+
+    let mut plant_grid = SpatialGrid::new(SPATIAL_CELL_SIZE);
+    for (i, plant) in plants.iter().enumerate() {
+        plant_grid.insert(i, plant.position, plant.size);
+    }
 
-    // We should check the closest entitie that is 20% bigger than us or the closest entity
-    // that is 20% smaller than us and move towards it or away from it respectively if it is
-    // within our sense distance.
+    let mut corpse_grid = SpatialGrid::new(SPATIAL_CELL_SIZE);
+    for (i, corpse) in corpses.iter().enumerate() {
+        corpse_grid.insert(i, corpse.position, corpse.size);
+    }
 
     let ent_len = entities.len();
     for i in 0..ent_len {
         let entity = &entities[i];
-
-        let mut closest_entity: Option<usize> = None;
-        let mut closest_entity_distance: f32 = std::f32::INFINITY;
+        let previous_position = entity.position;
+        let range = entity.sense_distance() * config.sense_range * sense_factor;
+        let eats_plants = entity.plant_efficiency(config) > 0.0;
+        let eats_meat = entity.meat_efficiency(config) > 0.0;
 
         // Get the closest entity
-        for j in 0..ent_len {
+        let nearest_entity = entity_grid.closest(entity.position, entity.size(), range, |j| {
             if i == j {
-                continue;
+                return None;
             }
 
             let other_entity = &entities[j];
-            let distance =
-                other_entity.position.distance(entity.position) - (other_entity.size + entity.size);
-
-            if distance < entity.sense_distance * 100.0 {
-                if distance < closest_entity_distance {
-                    closest_entity = Some(j);
-                    closest_entity_distance = distance;
-                }
-            }
-        }
+            Some(
+                other_entity.position.distance(entity.position)
+                    - (other_entity.size() + entity.size()),
+            )
+        });
 
         // Get the closest plant
-        let mut closest_plant: Option<usize> = None;
-        let mut closest_plant_distance: f32 = std::f32::INFINITY;
-
-        for j in 0..plants.len() {
+        let nearest_plant = plant_grid.closest(entity.position, entity.size(), range, |j| {
             let plant = &plants[j];
-            let distance = plant.position.distance(entity.position) - (entity.size + plant.size);
 
-            if distance < entity.sense_distance * 100.0 {
-                if distance < closest_plant_distance {
-                    closest_plant = Some(j);
-                    closest_plant_distance = distance;
-                }
+            // Entities that follow the rules know better than to eat poison, or what they can't
+            // digest.
+            if config.behavior == Behavior::Rules
+                && (plant.toxicity() >= config.poisonous_toxicity || !eats_plants)
+            {
+                return None;
             }
-        }
 
-        let mut moved = false;
-
-        if let Some(other_entity_index) = closest_entity {
-            let mut entity = entities[i];
-            let mut other_entity = entities[other_entity_index];
-
-            // Entities 20% bigger than us can eat us and we can eat entities 20% smaller than us
-            // so we should move towards entities 20% smaller than us and move away from entities 20% bigger than us
-
-            // If the distance is negative or zero then we are overlapping with the entity
-            // that means we have been eaten.
-            if closest_entity_distance <= 0.0 {
-                if other_entity.size > entity.size * 1.2 {
-                    // We are overlapping with an entity that is 20% bigger than us
-                    // so we have been eaten.
-                } else if other_entity.size <= entity.size * 0.8 {
-                    // We are overlapping with an entity that is 20% smaller than us
-                    // so we should eat it.
-                    entity.eat(&mut other_entity);
-                } else {
-                    // We are overlapping with an entity that is roughly the same size as us
-                    // so we should move away from it.
-                    entity.move_away(other_entity.position, dt);
-                    moved = true;
-                }
-            } else {
-                if other_entity.size > entity.size * 1.2 {
-                    // Move away from bigger entities
-                    entity.move_away(other_entity.position, dt);
-                    moved = true;
-                } else if other_entity.size < entity.size * 0.8 {
-                    // Move towards smaller entities
-                    entity.move_towards(other_entity.position, closest_entity_distance, dt);
-                    moved = true;
-                }
+            Some(plant.position.distance(entity.position) - (entity.size() + plant.size))
+        });
+
+        // Get the closest corpse that is still there
+        let nearest_corpse = corpse_grid.closest(entity.position, entity.size(), range, |j| {
+            let corpse = &corpses[j];
+            if corpse.is_gone(config) || (config.behavior == Behavior::Rules && !eats_meat) {
+                return None;
             }
 
-            entities[i] = entity;
-            entities[other_entity_index] = other_entity;
+            Some(corpse.position.distance(entity.position) - (entity.size() + corpse.size))
+        });
+
+        let nearest = Nearest {
+            range,
+            entity: nearest_entity,
+            plant: nearest_plant,
+            corpse: nearest_corpse,
+        };
+
+        let food = Food { plants, corpses };
+        match config.behavior {
+            Behavior::Rules => follow_rules(entities, food, i, nearest, config, dt, rng),
+            Behavior::Neural => follow_brain(entities, food, i, nearest, layout, config, dt),
         }
 
-        if let Some(plant_index) = closest_plant {
-            let mut entity = entities[i];
-            let mut plant = plants[plant_index];
+        entity_grid.relocate(
+            i,
+            previous_position,
+            entities[i].position,
+            entities[i].size(),
+        );
+    }
 
-            // If the distance is negative or zero then we are overlapping with the plant
-            // that means we have eaten it.
-            if closest_plant_distance <= 0.0 {
-                entity.eat_plant(&mut plant);
-                moved = true;
+    // Add children
+    add_children(entities, layout, config, rng);
+}
+
+// The closest entity and the closest plant within sense distance of an entity, with their
+// distance.
+#[derive(Clone, Copy)]
+struct Nearest {
+    range: f32,
+    entity: Option<(usize, f32)>,
+    plant: Option<(usize, f32)>,
+    corpse: Option<(usize, f32)>,
+}
+
+/// What entities can eat besides each other.
+pub struct Food<'a> {
+    pub plants: &'a mut [Plant],
+    pub corpses: &'a mut [Corpse],
+}
+
+// An entity does not interact with entities that are roughly the same size as it
+// because they are not a threat and they are not food.
+
+// We should check the closest entitie that is 20% bigger than us or the closest entity
+// that is 20% smaller than us and move towards it or away from it respectively if it is
+// within our sense distance.
+fn follow_rules(
+    entities: &mut [Entity],
+    food: Food,
+    i: usize,
+    nearest: Nearest,
+    config: &SimConfig,
+    dt: f32,
+    rng: &mut impl Rng,
+) {
+    let mut moved = false;
+
+    if let Some((other_entity_index, closest_entity_distance)) = nearest.entity {
+        let (entity, other_entity) = pair_mut(entities, i, other_entity_index);
+
+        // Entities 20% bigger than us can eat us and we can eat entities 20% smaller than us
+        // so we should move towards entities 20% smaller than us and move away from entities 20% bigger than us
+
+        // If the distance is negative or zero then we are overlapping with the entity
+        // that means we have been eaten.
+        if closest_entity_distance <= 0.0 {
+            if other_entity.size() > entity.size() * config.predator_ratio {
+                // We are overlapping with an entity that is 20% bigger than us
+                // so we have been eaten.
+            } else if other_entity.size() <= entity.size() * config.prey_ratio
+                && entity.meat_efficiency(config) > 0.0
+            {
+                // We are overlapping with an entity that is 20% smaller than us
+                // and we can digest it so we should eat it.
+                entity.eat(other_entity, config);
             } else {
-                entity.move_towards(plant.position, closest_plant_distance, dt);
+                // We are overlapping with an entity that is roughly the same size as us
+                // so we should move away from it.
+                entity.move_away(other_entity.position, dt, config);
                 moved = true;
             }
+        } else if other_entity.size() > entity.size() * config.predator_ratio {
+            // Move away from bigger entities
+            entity.move_away(other_entity.position, dt, config);
+            moved = true;
+        } else if other_entity.size() < entity.size() * config.prey_ratio
+            && entity.meat_efficiency(config) > 0.0
+        {
+            // Move towards smaller entities we can digest
+            entity.move_towards(other_entity.position, closest_entity_distance, dt, config);
+            moved = true;
+        }
+    }
+
+    // Scavenge the closest corpse if it is closer than the closest plant.
+    let corpse = nearest.corpse.filter(|(_, corpse_distance)| {
+        nearest
+            .plant
+            .is_none_or(|(_, plant_distance)| *corpse_distance < plant_distance)
+    });
+
+    if let Some((corpse_index, closest_corpse_distance)) = corpse {
+        let entity = &mut entities[i];
+        let corpse = &mut food.corpses[corpse_index];
 
-            entities[i] = entity;
-            plants[plant_index] = plant;
+        if closest_corpse_distance <= 0.0 {
+            entity.eat_corpse(corpse, config);
+        } else {
+            entity.move_towards(corpse.position, closest_corpse_distance, dt, config);
+        }
+        moved = true;
+    } else if let Some((plant_index, closest_plant_distance)) = nearest.plant {
+        let entity = &mut entities[i];
+        let plant = &mut food.plants[plant_index];
+
+        // If the distance is negative or zero then we are overlapping with the plant
+        // that means we have eaten it.
+        if closest_plant_distance <= 0.0 {
+            entity.eat_plant(plant, config);
+            moved = true;
+        } else {
+            entity.move_towards(plant.position, closest_plant_distance, dt, config);
+            moved = true;
         }
+    }
+
+    if !moved {
+        // If there are no plants within our sense distance then we should move randomly
+        // in a random direction.
+        entities[i].move_randomly(dt, config, rng);
+    }
+}
 
-        if !moved {
-            // If there are no plants within our sense distance then we should move randomly
-            // in a random direction.
-            entities[i].move_randomly(dt);
+fn follow_brain(
+    entities: &mut [Entity],
+    food: Food,
+    i: usize,
+    nearest: Nearest,
+    layout: &GenomeLayout,
+    config: &SimConfig,
+    dt: f32,
+) {
+    let senses = sense(entities, food.plants, i, nearest, config);
+
+    // Whatever the brain decides, we eat the smaller entities, the plants and the corpses we
+    // touch, as long as we can digest them. Brains don't sense corpses, so they only scavenge by
+    // chance.
+    let eats_plants = entities[i].plant_efficiency(config) > 0.0;
+    let eats_meat = entities[i].meat_efficiency(config) > 0.0;
+
+    if let Some((j, distance)) = nearest.entity {
+        let (entity, other_entity) = pair_mut(entities, i, j);
+        if eats_meat && distance <= 0.0 && other_entity.size() <= entity.size() * config.prey_ratio
+        {
+            entity.eat(other_entity, config);
         }
     }
 
-    // Add children
-    add_children(entities);
+    if let Some((j, distance)) = nearest.plant {
+        if eats_plants && distance <= 0.0 {
+            entities[i].eat_plant(&mut food.plants[j], config);
+        }
+    }
+
+    if let Some((j, distance)) = nearest.corpse {
+        if eats_meat && distance <= 0.0 {
+            entities[i].eat_corpse(&mut food.corpses[j], config);
+        }
+    }
+
+    let entity = &mut entities[i];
+    let weights = match layout.brain() {
+        Some(range) => entity.genome.slice(range),
+        None => return,
+    };
+    let decision = think(weights, layout.brain_hidden_neurons(), &senses);
+    entity.steer(decision, dt, config);
+}
+
+fn sense(
+    entities: &[Entity],
+    plants: &[Plant],
+    i: usize,
+    nearest: Nearest,
+    config: &SimConfig,
+) -> Senses {
+    let entity = &entities[i];
+    let range = nearest.range;
+
+    let proximity = |distance: f32| {
+        if range > 0.0 {
+            (1.0 - distance / range).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    };
+    let direction = |target: Vec2| (target - entity.position).normalize_or_zero();
+
+    let mut senses = Senses {
+        energy: (entity.energy / (entity.energy_cost() * config.reproduction_threshold)).min(1.0),
+        ..Senses::default()
+    };
+
+    if let Some((j, distance)) = nearest.plant {
+        senses.plant_proximity = proximity(distance);
+        senses.plant_direction = direction(plants[j].position);
+    }
+
+    if let Some((j, distance)) = nearest.entity {
+        let other_entity = &entities[j];
+        senses.entity_proximity = proximity(distance);
+        senses.entity_direction = direction(other_entity.position);
+        senses.entity_relative_size = other_entity.size() / entity.size() - 1.0;
+    }
+
+    senses
+}
+
+// Mutable references to two different entities.
+fn pair_mut(entities: &mut [Entity], i: usize, j: usize) -> (&mut Entity, &mut Entity) {
+    if i < j {
+        let (left, right) = entities.split_at_mut(j);
+        (&mut left[i], &mut right[0])
+    } else {
+        let (left, right) = entities.split_at_mut(i);
+        (&mut right[0], &mut left[j])
+    }
 }
 
-fn linear_interpolate_vec(a: Vec3, b: Vec3, t: f32) -> Vec3 {
-    a + (b - a) * t
+// Fraction of the energy of food that an entity with `preference` for it digests, see
+// `SimConfig::diet_specialization`.
+fn digestion(preference: f32, config: &SimConfig) -> f32 {
+    (2.0 * preference).powf(config.diet_specialization)
 }
 
-fn linear_interpolate_f32(a: f32, b: f32, t: f32) -> f32 {
-    a + (b - a) * t
+/// A random unit vector.
+pub fn random_direction(rng: &mut impl Rng) -> Vec2 {
+    Vec2::new(rng.gen::<f32>() * 2.0 - 1.0, rng.gen::<f32>() * 2.0 - 1.0).normalize()
 }
@@ -0,0 +1,435 @@
+//! Running many worlds to see how the config shapes evolution.
+//!
+//! An `Experiment` varies some numeric fields of a base config, either over every combination
+//! of their values or at random points between their extremes, and runs one headless world per
+//! point and seed. `ExperimentResults::to_csv` aggregates the runs of every point.
+
+use std::fmt;
+use std::fmt::Write;
+
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::SimConfig;
+use crate::genome::GenomeLayout;
+use crate::simulation::Simulation;
+
+/// A numeric field of `SimConfig` and the values it takes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Parameter {
+    pub name: String,
+    /// Every value, for `Sampling::Grid`. `Sampling::Random` draws between the smallest and the
+    /// biggest. Integer fields are rounded.
+    pub values: Vec<f64>,
+}
+
+/// Which points of the parameter space are run.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Sampling {
+    /// Every combination of the values of the parameters.
+    #[default]
+    Grid,
+    /// `samples` points, every parameter drawn uniformly. `seed` picks the points.
+    Random {
+        samples: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Experiment {
+    /// The config of every run, before the parameters are applied.
+    #[serde(default)]
+    pub base: SimConfig,
+    #[serde(default)]
+    pub parameters: Vec<Parameter>,
+    #[serde(default)]
+    pub sampling: Sampling,
+    /// Every point is run once per seed.
+    #[serde(default = "first_seed")]
+    pub seeds: Vec<u64>,
+    /// Most steps a run lasts. It stops earlier if every entity dies.
+    pub ticks: u64,
+}
+
+fn first_seed() -> Vec<u64> {
+    vec![0]
+}
+
+/// The parameters of one point and the config they make.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    /// The value of every parameter, in the order of `Experiment::parameters`.
+    pub values: Vec<f64>,
+    pub config: SimConfig,
+}
+
+/// How one world ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    pub seed: u64,
+    /// The tick the last entity died, or the length of the run if some survived.
+    pub survival_ticks: u64,
+    pub extinct: bool,
+    pub final_entities: usize,
+    pub final_plants: usize,
+    /// The mean of every gene of `ExperimentResults::genes` over the surviving entities, NaN
+    /// when none survived.
+    pub gene_means: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointResults {
+    pub point: Point,
+    /// One per seed, in the order of `Experiment::seeds`.
+    pub runs: Vec<RunSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentResults {
+    /// The names of the parameters.
+    pub parameters: Vec<String>,
+    /// The genes that means are collected for: every gene but the weights of brains.
+    pub genes: Vec<String>,
+    pub points: Vec<PointResults>,
+}
+
+#[derive(Debug)]
+pub enum ExperimentError {
+    /// The JSON could not be parsed into an experiment.
+    Parse(String),
+    /// A parameter or a point is not valid.
+    Invalid(String),
+}
+
+impl fmt::Display for ExperimentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExperimentError::Parse(message) => write!(f, "invalid experiment: {}", message),
+            ExperimentError::Invalid(message) => write!(f, "invalid experiment: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ExperimentError {}
+
+impl Experiment {
+    pub fn from_json(json: &str) -> Result<Self, ExperimentError> {
+        let experiment: Experiment = serde_json::from_str(json)
+            .map_err(|error| ExperimentError::Parse(error.to_string()))?;
+        experiment.points()?;
+
+        Ok(experiment)
+    }
+
+    /// The points to run, each config validated.
+    pub fn points(&self) -> Result<Vec<Point>, ExperimentError> {
+        if self.seeds.is_empty() {
+            return Err(ExperimentError::Invalid(
+                "seeds must not be empty".to_string(),
+            ));
+        }
+
+        for parameter in self.parameters.iter() {
+            if parameter.values.is_empty() || parameter.values.iter().any(|v| !v.is_finite()) {
+                return Err(ExperimentError::Invalid(format!(
+                    "parameter {} needs at least one finite value",
+                    parameter.name
+                )));
+            }
+        }
+
+        let values: Vec<Vec<f64>> = match self.sampling {
+            Sampling::Grid => {
+                // Like nested loops, the last parameter changing fastest.
+                let mut points = vec![vec![]];
+                for parameter in self.parameters.iter() {
+                    points = points
+                        .into_iter()
+                        .flat_map(|point: Vec<f64>| {
+                            parameter.values.iter().map(move |value| {
+                                let mut point = point.clone();
+                                point.push(*value);
+                                point
+                            })
+                        })
+                        .collect();
+                }
+                points
+            }
+            Sampling::Random { samples, seed } => {
+                let mut rng = Pcg32::seed_from_u64(seed);
+                (0..samples)
+                    .map(|_| {
+                        self.parameters
+                            .iter()
+                            .map(|parameter| {
+                                let min = parameter.values.iter().copied().fold(f64::MAX, f64::min);
+                                let max = parameter.values.iter().copied().fold(f64::MIN, f64::max);
+                                min + rng.gen::<f64>() * (max - min)
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+        };
+
+        values
+            .into_iter()
+            .map(|values| self.point(values))
+            .collect()
+    }
+
+    // Applies `values` to the base config, going through JSON so that any numeric field can be
+    // a parameter.
+    fn point(&self, mut values: Vec<f64>) -> Result<Point, ExperimentError> {
+        let mut json = serde_json::to_value(&self.base).expect("a config can always be encoded");
+
+        for (parameter, value) in self.parameters.iter().zip(values.iter_mut()) {
+            let field = json.get_mut(&parameter.name).ok_or_else(|| {
+                ExperimentError::Invalid(format!("no config field named {}", parameter.name))
+            })?;
+
+            *field = match field {
+                Value::Number(number) if number.is_u64() => {
+                    *value = value.round().max(0.0);
+                    Value::from(*value as u64)
+                }
+                Value::Number(_) => Value::from(*value),
+                _ => {
+                    return Err(ExperimentError::Invalid(format!(
+                        "config field {} is not a number",
+                        parameter.name
+                    )))
+                }
+            };
+        }
+
+        let config: SimConfig = serde_json::from_value(json)
+            .map_err(|error| ExperimentError::Invalid(error.to_string()))?;
+        config
+            .validate()
+            .map_err(|error| ExperimentError::Invalid(format!("at {:?}: {}", values, error)))?;
+
+        Ok(Point { values, config })
+    }
+
+    /// Runs every point once per seed, on `threads` threads (all the cores when 0). Runs are
+    /// independent, so the results don't depend on the number of threads.
+    pub fn run(&self, threads: usize) -> Result<ExperimentResults, ExperimentError> {
+        let points = self.points()?;
+        let jobs: Vec<(usize, u64)> = (0..points.len())
+            .flat_map(|point| self.seeds.iter().map(move |seed| (point, *seed)))
+            .collect();
+
+        let summaries = run_jobs(&jobs, threads, |(point, seed)| {
+            run_one(&points[point].config, seed, self.ticks)
+        });
+
+        let layout = GenomeLayout::new(&self.base);
+        let brain = layout.brain().unwrap_or(0..0);
+        let genes = layout
+            .genes()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !brain.contains(i))
+            .map(|(_, gene)| gene.name.clone())
+            .collect();
+
+        let mut summaries = summaries.into_iter();
+        let points = points
+            .into_iter()
+            .map(|point| PointResults {
+                point,
+                runs: summaries.by_ref().take(self.seeds.len()).collect(),
+            })
+            .collect();
+
+        Ok(ExperimentResults {
+            parameters: self
+                .parameters
+                .iter()
+                .map(|parameter| parameter.name.clone())
+                .collect(),
+            genes,
+            points,
+        })
+    }
+}
+
+/// Runs a world with `config` and `seed` for at most `ticks` steps.
+pub fn run_one(config: &SimConfig, seed: u64, ticks: u64) -> RunSummary {
+    let mut simulation = Simulation::with_config(seed, config.clone());
+
+    while simulation.tick() < ticks && !simulation.entities().is_empty() {
+        simulation.step(config.time_step);
+    }
+
+    let layout = simulation.genome_layout();
+    let brain = layout.brain().unwrap_or(0..0);
+    let entities = simulation.entities();
+    let gene_means = (0..layout.len())
+        .filter(|i| !brain.contains(i))
+        .map(|i| {
+            entities
+                .iter()
+                .map(|entity| entity.genome().get(i))
+                .sum::<f32>()
+                / entities.len() as f32
+        })
+        .collect();
+
+    RunSummary {
+        seed,
+        survival_ticks: simulation.tick(),
+        extinct: entities.is_empty(),
+        final_entities: entities.len(),
+        final_plants: simulation.plants().len(),
+        gene_means,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_jobs<J, R, F>(jobs: &[J], threads: usize, run: F) -> Vec<R>
+where
+    J: Copy + Sync,
+    R: Send,
+    F: Fn(J) -> R + Sync,
+{
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    let threads = match threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .min(jobs.len())
+    .max(1);
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(jobs.iter().map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let job = match jobs.get(i) {
+                    Some(job) => *job,
+                    None => break,
+                };
+                let result = run(job);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every job ran"))
+        .collect()
+}
+
+// Browsers have no threads to spare, runs go one after the other.
+#[cfg(target_arch = "wasm32")]
+fn run_jobs<J, R, F>(jobs: &[J], _threads: usize, run: F) -> Vec<R>
+where
+    J: Copy + Sync,
+    R: Send,
+    F: Fn(J) -> R + Sync,
+{
+    jobs.iter().map(|job| run(*job)).collect()
+}
+
+impl ExperimentResults {
+    /// One row per point: the parameters, the number of runs and of extinctions, then the mean
+    /// over the runs of the survival time, of the final numbers of entities and plants, and of
+    /// every gene mean (over the runs with survivors, empty if none had any).
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for name in self.parameters.iter() {
+            write!(csv, "{},", name).unwrap();
+        }
+        csv.push_str("runs,extinctions,survival_ticks,final_entities,final_plants");
+        for gene in self.genes.iter() {
+            write!(csv, ",{}_mean", gene).unwrap();
+        }
+        csv.push('\n');
+
+        for point in self.points.iter() {
+            let runs = &point.runs;
+            let mean = |value: &dyn Fn(&RunSummary) -> f64| {
+                runs.iter().map(value).sum::<f64>() / runs.len() as f64
+            };
+
+            for value in point.point.values.iter() {
+                write!(csv, "{},", value).unwrap();
+            }
+            write!(
+                csv,
+                "{},{},{},{},{}",
+                runs.len(),
+                runs.iter().filter(|run| run.extinct).count(),
+                mean(&|run| run.survival_ticks as f64),
+                mean(&|run| run.final_entities as f64),
+                mean(&|run| run.final_plants as f64),
+            )
+            .unwrap();
+
+            let survived: Vec<&RunSummary> = runs.iter().filter(|run| !run.extinct).collect();
+            for gene in 0..self.genes.len() {
+                csv.push(',');
+                if !survived.is_empty() {
+                    let sum: f32 = survived.iter().map(|run| run.gene_means[gene]).sum();
+                    write!(csv, "{}", sum / survived.len() as f32).unwrap();
+                }
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// One row per run: the parameters, the seed, how the run ended and its gene means.
+    pub fn runs_to_csv(&self) -> String {
+        let mut csv = String::new();
+        for name in self.parameters.iter() {
+            write!(csv, "{},", name).unwrap();
+        }
+        csv.push_str("seed,extinct,survival_ticks,final_entities,final_plants");
+        for gene in self.genes.iter() {
+            write!(csv, ",{}_mean", gene).unwrap();
+        }
+        csv.push('\n');
+
+        for point in self.points.iter() {
+            for run in point.runs.iter() {
+                for value in point.point.values.iter() {
+                    write!(csv, "{},", value).unwrap();
+                }
+                write!(
+                    csv,
+                    "{},{},{},{},{}",
+                    run.seed, run.extinct, run.survival_ticks, run.final_entities, run.final_plants
+                )
+                .unwrap();
+                for mean in run.gene_means.iter() {
+                    csv.push(',');
+                    if !run.extinct {
+                        write!(csv, "{}", mean).unwrap();
+                    }
+                }
+                csv.push('\n');
+            }
+        }
+
+        csv
+    }
+}
@@ -0,0 +1,380 @@
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::brain::{brain_weights, Behavior, BRAIN_WEIGHT_LIMIT};
+use crate::config::SimConfig;
+
+/// Index of the `size` gene, which every genome starts with.
+pub const SIZE: usize = 0;
+/// Index of the `sense_distance` gene.
+pub const SENSE_DISTANCE: usize = 1;
+/// Index of the `velocity_mag` gene.
+pub const VELOCITY_MAG: usize = 2;
+
+/// Index of the `diet` gene, which follows the built-in genes when
+/// `SimConfig::evolving_diet`.
+pub const DIET: usize = 3;
+
+/// Names of the genes every genome starts with, in order.
+pub const BUILT_IN_GENES: [&str; 3] = ["size", "sense_distance", "velocity_mag"];
+
+/// Index of the `max_size` gene, which every plant genome starts with.
+pub const PLANT_MAX_SIZE: usize = 0;
+/// Index of the `seed_count` gene.
+pub const SEED_COUNT: usize = 1;
+/// Index of the `seed_distance` gene.
+pub const SEED_DISTANCE: usize = 2;
+/// Index of the `growth_speed` gene.
+pub const GROWTH_SPEED: usize = 3;
+/// Index of the `toxicity` gene.
+pub const TOXICITY: usize = 4;
+
+/// Names of the genes every plant genome starts with, in order.
+pub const BUILT_IN_PLANT_GENES: [&str; 5] = [
+    "max_size",
+    "seed_count",
+    "seed_distance",
+    "growth_speed",
+    "toxicity",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeneKind {
+    /// Any value in the range of the gene.
+    #[default]
+    Real,
+    /// A whole number in the range of the gene.
+    Integer,
+    /// 0 or 1. A mutation flips it, whatever the distribution.
+    Flag,
+}
+
+/// How a gene changes when it mutates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "distribution", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Mutation {
+    /// Scales the value by a random factor in `1 ± amount`.
+    Relative { amount: f32 },
+    /// Adds a random amount in `± amount`.
+    Uniform { amount: f32 },
+    /// Adds normally distributed noise.
+    Gaussian { sigma: f32 },
+}
+
+impl Default for Mutation {
+    fn default() -> Self {
+        Mutation::Relative { amount: 0.1 }
+    }
+}
+
+/// The description of one gene: its name, its range and how it mutates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeneSpec {
+    pub name: String,
+    #[serde(default)]
+    pub kind: GeneKind,
+    pub min: f32,
+    pub max: f32,
+    #[serde(default)]
+    pub mutation: Mutation,
+    /// Chance that the gene mutates when it is passed to a child.
+    #[serde(default = "always")]
+    pub rate: f32,
+}
+
+fn always() -> f32 {
+    1.0
+}
+
+impl GeneSpec {
+    fn random_value(&self, rng: &mut impl Rng) -> f32 {
+        match self.kind {
+            GeneKind::Flag => (rng.gen::<f32>() < 0.5) as u8 as f32,
+            _ => self.fit(self.min + rng.gen::<f32>() * (self.max - self.min)),
+        }
+    }
+
+    fn mutate(&self, value: f32, rng: &mut impl Rng) -> f32 {
+        // Skip the draw when it can't fail, so that genes which always mutate don't pay for it.
+        if self.rate < 1.0 && rng.gen::<f32>() >= self.rate {
+            return value;
+        }
+
+        if self.kind == GeneKind::Flag {
+            return 1.0 - value;
+        }
+
+        let mutated = match self.mutation {
+            Mutation::Relative { amount } => {
+                value * (1.0 + rng.gen::<f32>() * (2.0 * amount) - amount)
+            }
+            Mutation::Uniform { amount } => value + rng.gen::<f32>() * (2.0 * amount) - amount,
+            Mutation::Gaussian { sigma } => value + sigma * standard_normal(rng),
+        };
+
+        self.fit(mutated)
+    }
+
+    // Clamps `value` to the range and rounds it to the kind of the gene.
+    fn fit(&self, value: f32) -> f32 {
+        match self.kind {
+            GeneKind::Real => value.clamp(self.min, self.max),
+            GeneKind::Integer => value.round().clamp(self.min.ceil(), self.max.floor()),
+            GeneKind::Flag => (value >= 0.5) as u8 as f32,
+        }
+    }
+
+    // The value of a gene that a genome lacks, e.g. one added to the config after a save.
+    fn middle(&self) -> f32 {
+        self.fit((self.min + self.max) / 2.0)
+    }
+}
+
+/// The genes every genome of a world has, in order. Built from the config: first the built-in
+/// genes, then `diet` when it evolves, then the weights of the brain (`brain_0`, `brain_1`, ..)
+/// when entities are neural, then `SimConfig::genes` that don't replace one of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenomeLayout {
+    genes: Vec<GeneSpec>,
+    brain: Option<Range<usize>>,
+    brain_hidden_neurons: usize,
+}
+
+impl GenomeLayout {
+    pub fn new(config: &SimConfig) -> Self {
+        let mut genes: Vec<GeneSpec> = BUILT_IN_GENES
+            .iter()
+            .map(|name| GeneSpec {
+                name: name.to_string(),
+                kind: GeneKind::Real,
+                min: config.min_trait,
+                max: config.max_trait,
+                mutation: Mutation::Relative {
+                    amount: config.mutation_amount,
+                },
+                rate: 1.0,
+            })
+            .collect();
+
+        if config.evolving_diet {
+            genes.push(GeneSpec {
+                name: "diet".to_string(),
+                kind: GeneKind::Real,
+                min: 0.0,
+                max: 1.0,
+                mutation: Mutation::Uniform {
+                    amount: config.mutation_amount,
+                },
+                rate: 1.0,
+            });
+        }
+
+        let brain = match config.behavior {
+            Behavior::Rules => None,
+            Behavior::Neural => {
+                let start = genes.len();
+                for i in 0..brain_weights(config.brain_hidden_neurons) {
+                    genes.push(GeneSpec {
+                        name: format!("brain_{}", i),
+                        kind: GeneKind::Real,
+                        min: -BRAIN_WEIGHT_LIMIT,
+                        max: BRAIN_WEIGHT_LIMIT,
+                        mutation: Mutation::Gaussian {
+                            sigma: config.brain_mutation,
+                        },
+                        rate: config.brain_mutation_rate,
+                    });
+                }
+                Some(start..genes.len())
+            }
+        };
+
+        for spec in config.genes.iter() {
+            match genes.iter_mut().find(|gene| gene.name == spec.name) {
+                Some(gene) => *gene = spec.clone(),
+                None => genes.push(spec.clone()),
+            }
+        }
+
+        Self {
+            genes,
+            brain,
+            brain_hidden_neurons: config.brain_hidden_neurons,
+        }
+    }
+
+    /// The genes of plants: none unless `SimConfig::evolving_plants`, then the built-in plant
+    /// genes followed by `SimConfig::plant_genes` that don't replace one of them.
+    pub fn for_plants(config: &SimConfig) -> Self {
+        if !config.evolving_plants {
+            return Self::default();
+        }
+
+        let gene = |name: &str, kind, min, max, amount| GeneSpec {
+            name: name.to_string(),
+            kind,
+            min,
+            max,
+            mutation: Mutation::Uniform { amount },
+            rate: 1.0,
+        };
+        let mut genes = vec![
+            gene("max_size", GeneKind::Real, 0.2, 1.0, 0.05),
+            gene("seed_count", GeneKind::Integer, 1.0, 4.0, 1.0),
+            gene("seed_distance", GeneKind::Real, 2.0, 20.0, 1.0),
+            gene("growth_speed", GeneKind::Real, 0.0, 0.02, 0.001),
+            gene("toxicity", GeneKind::Real, 0.0, 1.0, 0.05),
+        ];
+        // Seed counts only change now and then, a change is a big step.
+        genes[SEED_COUNT].rate = 0.2;
+
+        for spec in config.plant_genes.iter() {
+            match genes.iter_mut().find(|gene| gene.name == spec.name) {
+                Some(gene) => *gene = spec.clone(),
+                None => genes.push(spec.clone()),
+            }
+        }
+
+        Self {
+            genes,
+            brain: None,
+            brain_hidden_neurons: 0,
+        }
+    }
+
+    pub fn genes(&self) -> &[GeneSpec] {
+        &self.genes
+    }
+
+    /// Where the weights of the brain are in a genome, `None` unless entities are neural.
+    pub fn brain(&self) -> Option<Range<usize>> {
+        self.brain.clone()
+    }
+
+    pub fn brain_hidden_neurons(&self) -> usize {
+        self.brain_hidden_neurons
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.genes.iter().position(|gene| gene.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.genes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genes.is_empty()
+    }
+}
+
+/// The heritable values of an entity, one per gene of the `GenomeLayout` of its world.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Genome {
+    values: Vec<f32>,
+}
+
+impl Genome {
+    pub fn new(values: Vec<f32>) -> Self {
+        Self { values }
+    }
+
+    /// Draws every gene uniformly in its range.
+    pub fn random(layout: &GenomeLayout, rng: &mut impl Rng) -> Self {
+        Self {
+            values: layout
+                .genes()
+                .iter()
+                .map(|gene| gene.random_value(rng))
+                .collect(),
+        }
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// The value of the gene at `index` in the layout.
+    pub fn get(&self, index: usize) -> f32 {
+        self.values[index]
+    }
+
+    /// The values of the genes in `range`.
+    pub fn slice(&self, range: Range<usize>) -> &[f32] {
+        &self.values[range]
+    }
+
+    pub fn gene(&self, layout: &GenomeLayout, name: &str) -> Option<f32> {
+        self.values.get(layout.index_of(name)?).copied()
+    }
+
+    /// A copy of this genome for a child, every gene mutated according to its spec.
+    pub fn mutated(&self, layout: &GenomeLayout, rng: &mut impl Rng) -> Self {
+        Self {
+            values: self
+                .values
+                .iter()
+                .zip(layout.genes())
+                .map(|(value, gene)| gene.mutate(*value, rng))
+                .collect(),
+        }
+    }
+
+    /// A genome that takes every gene from either parent with the same chance.
+    pub fn crossover(&self, other: &Genome, rng: &mut impl Rng) -> Self {
+        Self {
+            values: self
+                .values
+                .iter()
+                .zip(other.values.iter())
+                .map(|(a, b)| if rng.gen::<bool>() { *a } else { *b })
+                .collect(),
+        }
+    }
+
+    /// How different two genomes are, from 0 (identical) to 1 (every gene at opposite ends of
+    /// its range): the root mean square of the differences, each relative to the range of its
+    /// gene. The weights of the brain are left out: they far outnumber the other genes and would
+    /// make species and mates a matter of brains alone.
+    pub fn distance(&self, other: &Genome, layout: &GenomeLayout) -> f32 {
+        let brain = layout.brain().unwrap_or(0..0);
+        let genes = layout.len() - brain.len();
+        if genes == 0 {
+            return 0.0;
+        }
+
+        let sum: f32 = self
+            .values
+            .iter()
+            .zip(other.values.iter())
+            .zip(layout.genes())
+            .enumerate()
+            .filter(|(i, (_, gene))| !brain.contains(i) && gene.max > gene.min)
+            .map(|(_, ((a, b), gene))| ((a - b) / (gene.max - gene.min)).powi(2))
+            .sum();
+
+        (sum / genes as f32).sqrt()
+    }
+
+    /// Adds the genes this genome lacks, at the middle of their range, and drops the extra ones.
+    pub fn conform(&mut self, layout: &GenomeLayout) {
+        self.values.truncate(layout.len());
+        for gene in layout.genes()[self.values.len()..].iter() {
+            self.values.push(gene.middle());
+        }
+    }
+}
+
+// Box-Muller transform, to avoid pulling in a distributions crate for one function.
+fn standard_normal(rng: &mut impl Rng) -> f32 {
+    // In (0, 1] so that the logarithm is finite.
+    let u1 = 1.0 - rng.gen::<f32>();
+    let u2 = rng.gen::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+/// A stable handle to an entity or a plant.
+///
+/// Indices into the `Vec`s of the world change whenever something dies or is born, ids don't.
+/// An id is a slot plus the generation of that slot: when the holder of an id goes away its
+/// slot can be reused, but with the next generation, so an id is never handed out twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Id {
+    slot: u32,
+    generation: u32,
+}
+
+impl Id {
+    /// The id of something that has not entered the world yet.
+    pub const UNASSIGNED: Id = Id {
+        slot: u32::MAX,
+        generation: u32::MAX,
+    };
+
+    pub fn slot(self) -> u32 {
+        self.slot
+    }
+
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+
+    /// Packs the id in a `u64` (generation in the high bits), e.g. to hand it to JS.
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.slot as u64
+    }
+
+    pub fn from_bits(bits: u64) -> Self {
+        Self {
+            slot: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+impl Default for Id {
+    fn default() -> Self {
+        Id::UNASSIGNED
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Slot {
+    generation: u32,
+    // Where the current holder of the slot is in its `Vec`.
+    index: Option<usize>,
+}
+
+/// Hands out ids and maps them to the current index of their holder.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdMap {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+impl IdMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new id, not mapped to any index yet.
+    pub fn allocate(&mut self) -> Id {
+        if let Some(slot) = self.free.pop() {
+            return Id {
+                slot,
+                generation: self.slots[slot as usize].generation,
+            };
+        }
+
+        self.slots.push(Slot {
+            generation: 0,
+            index: None,
+        });
+
+        Id {
+            slot: self.slots.len() as u32 - 1,
+            generation: 0,
+        }
+    }
+
+    /// Forgets `id`. Its slot is reused with the next generation.
+    pub fn release(&mut self, id: Id) {
+        if !self.is_live(id) {
+            return;
+        }
+
+        let slot = &mut self.slots[id.slot as usize];
+        slot.index = None;
+
+        // A slot whose generations are exhausted is retired, so that ids are never reused.
+        if slot.generation < u32::MAX - 1 {
+            slot.generation += 1;
+            self.free.push(id.slot);
+        } else {
+            slot.generation = u32::MAX;
+        }
+    }
+
+    pub fn set_index(&mut self, id: Id, index: usize) {
+        if self.is_live(id) {
+            self.slots[id.slot as usize].index = Some(index);
+        }
+    }
+
+    /// Gives a new id to every `Id::UNASSIGNED` in `ids` and maps each id to its position in
+    /// `ids`, i.e. to the index of its holder.
+    pub fn index<'a>(&mut self, ids: impl Iterator<Item = &'a mut Id>) {
+        for (index, id) in ids.enumerate() {
+            if *id == Id::UNASSIGNED {
+                *id = self.allocate();
+            }
+            self.set_index(*id, index);
+        }
+    }
+
+    /// The current index of the holder of `id`, or `None` if it is gone.
+    pub fn index_of(&self, id: Id) -> Option<usize> {
+        if !self.is_live(id) {
+            return None;
+        }
+
+        self.slots[id.slot as usize].index
+    }
+
+    fn is_live(&self, id: Id) -> bool {
+        match self.slots.get(id.slot as usize) {
+            Some(slot) => slot.generation == id.generation && id != Id::UNASSIGNED,
+            None => false,
+        }
+    }
+}
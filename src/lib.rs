@@ -1,5 +1,54 @@
-mod utils;
+mod app;
+mod brain;
+mod clock;
+mod config;
 mod entity;
+mod experiment;
+mod genome;
+mod id;
+mod lineage;
+mod render;
+mod simulation;
+mod snapshot;
+mod spatial;
+mod species;
+mod stats;
+mod terrain;
+mod utils;
+mod world;
+
+pub use app::App;
+pub use brain::{
+    brain_weights, think, Behavior, Decision, Senses, BRAIN_INPUTS, BRAIN_OUTPUTS,
+    BRAIN_WEIGHT_LIMIT,
+};
+pub use clock::{Clock, Season};
+pub use config::{ConfigError, Reproduction, SimConfig};
+pub use entity::{Corpse, Entity, Food, Plant};
+pub use experiment::{
+    run_one, Experiment, ExperimentError, ExperimentResults, Parameter, Point, PointResults,
+    RunSummary, Sampling,
+};
+pub use genome::{
+    GeneKind, GeneSpec, Genome, GenomeLayout, Mutation, BUILT_IN_GENES, BUILT_IN_PLANT_GENES, DIET,
+    GROWTH_SPEED, PLANT_MAX_SIZE, SEED_COUNT, SEED_DISTANCE, SENSE_DISTANCE, SIZE, TOXICITY,
+    VELOCITY_MAG,
+};
+pub use id::{Id, IdMap};
+pub use lineage::{Lineage, LineageRecord};
+pub use render::{
+    draw_world, rgba, species_color, ColorMode, RenderBuffer, Shape, GEOMETRY_STRIDE,
+};
+pub use simulation::Simulation;
+pub use snapshot::{SnapshotError, SNAPSHOT_VERSION};
+pub use spatial::SpatialGrid;
+pub use species::{Species, SpeciesId, SpeciesTracker};
+pub use stats::{Sample, Stats, Trait, TraitStats, HISTOGRAM_BINS};
+pub use terrain::{fractal_noise, Biome, Terrain, TerrainBlock, TerrainKind, MAX_FERTILITY};
+pub use world::{
+    block_to_world, screen_to_world, world_to_block, world_to_screen, BLOCK_SIZE, GRID_SIZE,
+    PIXELS_PER_UNIT, WORLD_HALF_EXTENT,
+};
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -0,0 +1,253 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::entity::Entity;
+use crate::genome::{Genome, GenomeLayout};
+use crate::id::Id;
+
+/// Everything remembered about one entity, dead or alive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageRecord {
+    pub id: Id,
+    pub parent: Option<Id>,
+    /// The mate of the parent, for children of sexual reproduction. The tree only follows
+    /// `parent`.
+    #[serde(default)]
+    pub other_parent: Option<Id>,
+    pub birth_tick: u64,
+    /// `None` while the entity is alive.
+    pub death_tick: Option<u64>,
+    pub genome: Genome,
+}
+
+impl LineageRecord {
+    fn new(entity: &Entity, birth_tick: u64) -> Self {
+        Self {
+            id: entity.id(),
+            parent: entity.parent_id(),
+            other_parent: entity.other_parent_id(),
+            birth_tick,
+            death_tick: None,
+            genome: entity.genome().clone(),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.death_tick.is_none()
+    }
+}
+
+/// The family tree of every entity that ever lived, or a forest when the world started with
+/// several entities. The roots are the entities that were added to the world rather than born.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<LineageRecord>", into = "Vec<LineageRecord>")]
+pub struct Lineage {
+    records: BTreeMap<Id, LineageRecord>,
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_birth(&mut self, entity: &Entity, tick: u64) {
+        self.records
+            .entry(entity.id())
+            .or_insert_with(|| LineageRecord::new(entity, tick));
+    }
+
+    pub fn record_death(&mut self, id: Id, tick: u64) {
+        if let Some(record) = self.records.get_mut(&id) {
+            record.death_tick.get_or_insert(tick);
+        }
+    }
+
+    pub fn get(&self, id: Id) -> Option<&LineageRecord> {
+        self.records.get(&id)
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        self.records.contains_key(&id)
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &LineageRecord> {
+        self.records.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Forgets every dead entity that has no living descendant, keeping the ancestry of the
+    /// living ones. Returns the number of records removed.
+    pub fn prune_extinct(&mut self) -> usize {
+        let mut kept = HashSet::new();
+
+        for record in self.records.values().filter(|record| record.is_alive()) {
+            let mut current = Some(record.id);
+            // Stop as soon as we reach an ancestor that another living entity already kept.
+            while let Some(id) = current {
+                if !kept.insert(id) {
+                    break;
+                }
+                current = self.records.get(&id).and_then(|record| record.parent);
+            }
+        }
+
+        let before = self.records.len();
+        self.records.retain(|id, _| kept.contains(id));
+        before - self.records.len()
+    }
+
+    /// The tree in Newick format. Every entity is a node labeled with its id as given by
+    /// `Id::to_bits`, and the length of its branch is the number of ticks between the birth of
+    /// its parent and its own. Roots hang from an unlabeled node so that a forest is one tree.
+    pub fn to_newick(&self) -> String {
+        let children = self.children();
+        let roots = self.roots();
+
+        let mut newick = String::from("(");
+        let mut stack: Vec<Visit> = vec![];
+        push_children(&mut stack, &roots);
+
+        // Lineages can be thousands of generations deep, so walk the tree without recursion.
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(id) => match children.get(&id) {
+                    Some(ids) => {
+                        newick.push('(');
+                        stack.push(Visit::Close(id));
+                        push_children(&mut stack, ids);
+                    }
+                    None => self.write_newick_label(&mut newick, id),
+                },
+                Visit::Comma => newick.push(','),
+                Visit::Close(id) => {
+                    newick.push(')');
+                    self.write_newick_label(&mut newick, id);
+                }
+            }
+        }
+
+        newick.push_str(");");
+        newick
+    }
+
+    /// Every record as a flat JSON array, children after their parents. Ids are `Id::to_bits`,
+    /// `parent`, `other_parent` and `death_tick` are `null` for roots, children of a single parent
+    /// and living entities respectively, and `genome` maps the name of every gene of `layout` to
+    /// its value.
+    pub fn to_json(&self, layout: &GenomeLayout) -> String {
+        let mut records: Vec<&LineageRecord> = self.records.values().collect();
+        records.sort_by_key(|record| (record.birth_tick, record.id));
+
+        let records: Vec<_> = records
+            .into_iter()
+            .map(|record| {
+                let genome: Map<String, Value> = layout
+                    .genes()
+                    .iter()
+                    .zip(record.genome.values())
+                    .map(|(gene, value)| (gene.name.clone(), json!(value)))
+                    .collect();
+
+                json!({
+                    "id": record.id.to_bits(),
+                    "parent": record.parent.map(Id::to_bits),
+                    "other_parent": record.other_parent.map(Id::to_bits),
+                    "birth_tick": record.birth_tick,
+                    "death_tick": record.death_tick,
+                    "genome": genome,
+                })
+            })
+            .collect();
+
+        serde_json::to_string(&records).expect("a lineage can always be encoded")
+    }
+
+    // The recorded children of every recorded entity, oldest first.
+    fn children(&self) -> BTreeMap<Id, Vec<Id>> {
+        let mut children: BTreeMap<Id, Vec<Id>> = BTreeMap::new();
+
+        for record in self.records.values() {
+            if let Some(parent) = record.parent.filter(|parent| self.contains(*parent)) {
+                children.entry(parent).or_default().push(record.id);
+            }
+        }
+
+        for ids in children.values_mut() {
+            ids.sort_by_key(|id| (self.records[id].birth_tick, *id));
+        }
+
+        children
+    }
+
+    // Records whose parent is unknown, oldest first.
+    fn roots(&self) -> Vec<Id> {
+        let mut roots: Vec<Id> = self
+            .records
+            .values()
+            .filter(|record| !record.parent.is_some_and(|parent| self.contains(parent)))
+            .map(|record| record.id)
+            .collect();
+        roots.sort_by_key(|id| (self.records[id].birth_tick, *id));
+        roots
+    }
+
+    fn write_newick_label(&self, newick: &mut String, id: Id) {
+        let record = &self.records[&id];
+        let parent_birth = record
+            .parent
+            .and_then(|parent| self.get(parent))
+            .map_or(0, |parent| parent.birth_tick);
+
+        write!(
+            newick,
+            "{}:{}",
+            id.to_bits(),
+            record.birth_tick.saturating_sub(parent_birth)
+        )
+        .unwrap();
+    }
+}
+
+enum Visit {
+    Enter(Id),
+    Comma,
+    Close(Id),
+}
+
+// Pushes `ids` so that they are popped in order, separated by commas.
+fn push_children(stack: &mut Vec<Visit>, ids: &[Id]) {
+    for (i, id) in ids.iter().enumerate().rev() {
+        stack.push(Visit::Enter(*id));
+        if i > 0 {
+            stack.push(Visit::Comma);
+        }
+    }
+}
+
+// Saved as a list, JSON maps can only have string keys.
+impl From<Vec<LineageRecord>> for Lineage {
+    fn from(records: Vec<LineageRecord>) -> Self {
+        Self {
+            records: records
+                .into_iter()
+                .map(|record| (record.id, record))
+                .collect(),
+        }
+    }
+}
+
+impl From<Lineage> for Vec<LineageRecord> {
+    fn from(lineage: Lineage) -> Self {
+        lineage.records.into_values().collect()
+    }
+}
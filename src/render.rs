@@ -0,0 +1,213 @@
+use glam::{Vec2, Vec3};
+
+use crate::config::SimConfig;
+use crate::entity::Entity;
+use crate::simulation::Simulation;
+use crate::species::SpeciesId;
+use crate::terrain::Biome;
+use crate::world::{block_to_world, world_to_screen, BLOCK_SIZE, PIXELS_PER_UNIT};
+
+// Create a color RGB(200, 181, 170) this is for not fertile terrain
+static INFERTILE_TERRAIN_COLOR: Vec3 = Vec3::new(200.0, 181.0, 170.0);
+static FERTILE_TERRAIN_COLOR: Vec3 = Vec3::new(58.0, 46.0, 39.0);
+static WATER_COLOR: u32 = rgba(0x3a, 0x6e, 0xa5, 0xff);
+static PLANT_COLOR: u32 = rgba(0x00, 0x4e, 0x00, 0xff);
+static CORPSE_COLOR: u32 = rgba(0x6b, 0x5e, 0x57, 0xff);
+
+/// Number of `f32`s per command in `RenderBuffer::geometry`: shape, x, y, size.
+pub const GEOMETRY_STRIDE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Shape {
+    /// A square centered on x, y whose side is `size`.
+    Rect = 0,
+    /// A circle centered on x, y whose radius is `size`.
+    Circle = 1,
+}
+
+/// A frame worth of draw commands, laid out flat so that JS can read them straight out of wasm
+/// memory: `GEOMETRY_STRIDE` floats per command in `geometry` and one packed RGBA color per
+/// command in `colors`. Coordinates are canvas pixels before the camera transform.
+#[derive(Default)]
+pub struct RenderBuffer {
+    geometry: Vec<f32>,
+    colors: Vec<u32>,
+}
+
+impl RenderBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.geometry.clear();
+        self.colors.clear();
+    }
+
+    pub fn push(&mut self, shape: Shape, center: Vec2, size: f32, color: u32) {
+        self.geometry
+            .extend_from_slice(&[shape as u32 as f32, center.x, center.y, size]);
+        self.colors.push(color);
+    }
+
+    pub fn geometry(&self) -> &[f32] {
+        &self.geometry
+    }
+
+    pub fn colors(&self) -> &[u32] {
+        &self.colors
+    }
+
+    /// Number of commands.
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+}
+
+/// What the color of an entity shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Its species, from `species_color`. Entities that have not been clustered yet fall back
+    /// to `Traits`.
+    #[default]
+    Species,
+    /// Its size as red, its sense distance as green and its velocity as blue.
+    Traits,
+    /// Its diet, from green for plant eaters to red for meat eaters.
+    Diet,
+}
+
+impl ColorMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "species" => Some(ColorMode::Species),
+            "traits" => Some(ColorMode::Traits),
+            "diet" => Some(ColorMode::Diet),
+            _ => None,
+        }
+    }
+}
+
+/// A color for every species. Hues are spread by the golden ratio, so that species with close
+/// ids, e.g. a species and the one that split from it, are easy to tell apart.
+pub fn species_color(id: SpeciesId) -> u32 {
+    let hue = (id as f32 * 0.618_034 + 0.1).fract();
+    let (r, g, b) = hsv_to_rgb(hue, 0.7, 0.95);
+    rgba(
+        (r * 255.0) as u8,
+        (g * 255.0) as u8,
+        (b * 255.0) as u8,
+        0xff,
+    )
+}
+
+/// Packs a color as `0xRRGGBBAA`.
+pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    (r as u32) << 24 | (g as u32) << 16 | (b as u32) << 8 | a as u32
+}
+
+/// Replaces the content of `buffer` with the terrain, the corpses, the entities and the plants, in
+/// that order.
+pub fn draw_world(simulation: &Simulation, color_mode: ColorMode, buffer: &mut RenderBuffer) {
+    buffer.clear();
+
+    // Draw terrain
+    for (x, row) in simulation.terrain().blocks().iter().enumerate() {
+        for (y, block) in row.iter().enumerate() {
+            let color = if block.biome() == Biome::Water {
+                WATER_COLOR
+            } else {
+                let color = linear_interpolate_vec(
+                    INFERTILE_TERRAIN_COLOR,
+                    FERTILE_TERRAIN_COLOR,
+                    block.fertility,
+                );
+                rgba(color.x as u8, color.y as u8, color.z as u8, 0xff)
+            };
+
+            buffer.push(
+                Shape::Rect,
+                world_to_screen(block_to_world(x, y)),
+                BLOCK_SIZE * PIXELS_PER_UNIT,
+                color,
+            );
+        }
+    }
+
+    // Draw corpses
+    for corpse in simulation.corpses().iter() {
+        buffer.push(
+            Shape::Circle,
+            world_to_screen(corpse.position()),
+            corpse.size() * PIXELS_PER_UNIT,
+            CORPSE_COLOR,
+        );
+    }
+
+    // Draw entities
+    for entity in simulation.entities().iter() {
+        buffer.push(
+            Shape::Circle,
+            world_to_screen(entity.position()),
+            entity.size() * PIXELS_PER_UNIT,
+            entity_color(entity, color_mode, simulation.config()),
+        );
+    }
+
+    // Draw plants
+    for plant in simulation.plants().iter() {
+        buffer.push(
+            Shape::Rect,
+            world_to_screen(plant.position()),
+            plant.size() * PIXELS_PER_UNIT,
+            PLANT_COLOR,
+        );
+    }
+}
+
+fn entity_color(entity: &Entity, color_mode: ColorMode, config: &SimConfig) -> u32 {
+    match (color_mode, entity.species()) {
+        (ColorMode::Species, Some(species)) => species_color(species),
+        (ColorMode::Diet, _) => {
+            let diet = entity.diet(config);
+            rgba(
+                (diet * 255.0) as u8,
+                ((1.0 - diet) * 255.0) as u8,
+                0x00,
+                0xff,
+            )
+        }
+        _ => rgba(
+            (entity.size() * 255.0) as u8,
+            (entity.sense_distance() * 255.0) as u8,
+            (entity.velocity_mag() * 255.0) as u8,
+            0xff,
+        ),
+    }
+}
+
+// `hue`, `saturation` and `value` in [0, 1].
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (f32, f32, f32) {
+    let sector = hue * 6.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    (r + m, g + m, b + m)
+}
+
+fn linear_interpolate_vec(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+    a + (b - a) * t
+}
@@ -0,0 +1,433 @@
+use glam::Vec2;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+use crate::config::{ConfigError, SimConfig};
+use crate::entity::{random_direction, update, Corpse, Entity, Food, Plant};
+use crate::genome::{Genome, GenomeLayout};
+use crate::id::{Id, IdMap};
+use crate::lineage::Lineage;
+use crate::species::SpeciesTracker;
+use crate::stats::Stats;
+use crate::terrain::{Biome, Terrain};
+
+/// Most steps `advance` takes in one call.
+static MAX_STEPS_PER_ADVANCE: u32 = 10;
+
+/// The evolution world on its own: entities, plants and the terrain they live on.
+///
+/// It knows nothing about the browser, so it can be stepped natively for tests and profiling.
+/// `App` wraps it to draw it and to feed it user input.
+///
+/// Every random choice is drawn from a single RNG stream seeded at construction, so the same
+/// seed and the same sequence of `dt` values always produce the same world.
+#[derive(Serialize, Deserialize)]
+pub struct Simulation {
+    entities: Vec<Entity>,
+    plants: Vec<Plant>,
+    #[serde(default)]
+    corpses: Vec<Corpse>,
+    terrain: Terrain,
+    rng: Pcg32,
+    #[serde(default)]
+    config: SimConfig,
+    #[serde(default)]
+    tick: u64,
+    // Milliseconds passed to `advance` that have not been simulated yet.
+    #[serde(default)]
+    accumulator: f64,
+    #[serde(default)]
+    entity_ids: IdMap,
+    #[serde(default)]
+    plant_ids: IdMap,
+    #[serde(default)]
+    lineage: Lineage,
+    #[serde(default)]
+    species: SpeciesTracker,
+    // Derived from the config.
+    #[serde(skip)]
+    genome_layout: GenomeLayout,
+    #[serde(skip)]
+    plant_layout: GenomeLayout,
+    // The history is not part of the world, so it is not saved.
+    #[serde(skip)]
+    stats: Stats,
+}
+
+impl Simulation {
+    pub fn new(seed: u64) -> Self {
+        Self::with_config(seed, SimConfig::default())
+    }
+
+    /// Creates a world tuned by `config`, populated with its initial entities and plants.
+    pub fn with_config(seed: u64, config: SimConfig) -> Self {
+        let mut simulation = Self {
+            entities: vec![],
+            plants: vec![],
+            corpses: vec![],
+            terrain: Terrain::generate(&config, config.terrain_seed.unwrap_or(seed)),
+            rng: Pcg32::seed_from_u64(seed),
+            tick: 0,
+            accumulator: 0.0,
+            entity_ids: IdMap::new(),
+            plant_ids: IdMap::new(),
+            lineage: Lineage::new(),
+            species: SpeciesTracker::new(),
+            genome_layout: GenomeLayout::new(&config),
+            plant_layout: GenomeLayout::for_plants(&config),
+            config,
+            stats: Stats::default(),
+        };
+
+        simulation.populate(
+            simulation.config.initial_entities,
+            simulation.config.initial_plants,
+        );
+
+        simulation
+    }
+
+    /// Scatters `entities` random entities and `plants` random plants around the center of the
+    /// world.
+    pub fn populate(&mut self, entities: usize, plants: usize) {
+        for _ in 0..entities {
+            let position = Vec2::new(
+                (self.rng.gen::<f32>() * 2.0 - 1.0) * 100.0,
+                (self.rng.gen::<f32>() * 2.0 - 1.0) * 100.0,
+            );
+            let genome = Genome::random(&self.genome_layout, &mut self.rng);
+
+            let entity = Entity::new(genome, position, 10000.0, &mut self.rng);
+            self.add_entity(entity);
+        }
+
+        for _ in 0..plants {
+            let position = Vec2::new(
+                (self.rng.gen::<f32>() * 2.0 - 1.0) * 100.0,
+                (self.rng.gen::<f32>() * 2.0 - 1.0) * 100.0,
+            );
+            let size = self.rng.gen::<f32>() * 0.1 + 0.1;
+            let genome = Genome::random(&self.plant_layout, &mut self.rng);
+
+            let mut plant = Plant::with_genome(genome, position, size);
+            // Stagger the ages so that the first plants don't all die on the same tick.
+            if self.config.plant_lifespan > 0 {
+                plant.age = self.rng.gen_range(0, self.config.plant_lifespan);
+            }
+            self.add_plant(plant);
+        }
+    }
+
+    pub fn entities(&self) -> &Vec<Entity> {
+        &self.entities
+    }
+
+    pub fn plants(&self) -> &Vec<Plant> {
+        &self.plants
+    }
+
+    pub fn corpses(&self) -> &[Corpse] {
+        &self.corpses
+    }
+
+    pub fn terrain(&self) -> &Terrain {
+        &self.terrain
+    }
+
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// The genes of every genome in this world.
+    pub fn genome_layout(&self) -> &GenomeLayout {
+        &self.genome_layout
+    }
+
+    /// The genes of every plant genome in this world, none unless `SimConfig::evolving_plants`.
+    pub fn plant_layout(&self) -> &GenomeLayout {
+        &self.plant_layout
+    }
+
+    /// Number of steps taken so far.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// The season and the time of day.
+    pub fn clock(&self) -> Clock {
+        Clock::new(self.tick, &self.config)
+    }
+
+    /// Every entity that ever lived and who its parent was.
+    pub fn lineage(&self) -> &Lineage {
+        &self.lineage
+    }
+
+    pub fn lineage_mut(&mut self) -> &mut Lineage {
+        &mut self.lineage
+    }
+
+    /// The species found by the last clustering.
+    pub fn species(&self) -> &SpeciesTracker {
+        &self.species
+    }
+
+    /// Groups the entities into species now rather than at the next `species_interval`.
+    pub fn cluster_species(&mut self) {
+        self.species.cluster(
+            &mut self.entities,
+            &self.genome_layout,
+            self.config.species_distance,
+            self.tick,
+        );
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn stats_mut(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+
+    /// The index of the living entity `id`, or `None` if it is dead.
+    pub fn entity_index(&self, id: Id) -> Option<usize> {
+        self.entity_ids.index_of(id)
+    }
+
+    pub fn entity(&self, id: Id) -> Option<&Entity> {
+        Some(&self.entities[self.entity_index(id)?])
+    }
+
+    /// The index of the plant `id`, or `None` if it has been eaten.
+    pub fn plant_index(&self, id: Id) -> Option<usize> {
+        self.plant_ids.index_of(id)
+    }
+
+    pub fn plant(&self, id: Id) -> Option<&Plant> {
+        Some(&self.plants[self.plant_index(id)?])
+    }
+
+    /// Adds `entity` to the world under a new id. Genes missing from its genome are set to the
+    /// middle of their range.
+    pub fn add_entity(&mut self, mut entity: Entity) {
+        entity.genome_mut().conform(&self.genome_layout);
+        entity.id = self.entity_ids.allocate();
+        self.entity_ids.set_index(entity.id, self.entities.len());
+        self.lineage.record_birth(&entity, self.tick);
+        self.entities.push(entity);
+    }
+
+    /// Adds `plant` to the world under a new id. Genes missing from its genome are set to the
+    /// middle of their range.
+    pub fn add_plant(&mut self, mut plant: Plant) {
+        plant.genome_mut().conform(&self.plant_layout);
+        plant.id = self.plant_ids.allocate();
+        self.plant_ids.set_index(plant.id, self.plants.len());
+        self.terrain.count_new_plant(plant.position());
+        self.plants.push(plant);
+    }
+
+    /// The world's RNG, for callers that create entities from outside of `step`.
+    pub fn rng(&mut self) -> &mut Pcg32 {
+        &mut self.rng
+    }
+
+    /// Lets `elapsed` milliseconds of real time pass, taking as many fixed `time_step` steps as
+    /// fit. The remainder is carried over to the next call, so the world evolves the same way
+    /// whatever the frame rate. Returns the number of steps taken.
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        let time_step = self.config.time_step as f64;
+
+        // Don't try to catch up after a long pause (e.g. a hidden tab), drop the lost time instead.
+        self.accumulator = (self.accumulator + elapsed.max(0.0) as f64)
+            .min(time_step * MAX_STEPS_PER_ADVANCE as f64);
+
+        let mut steps = 0;
+        while self.accumulator >= time_step {
+            self.step(self.config.time_step);
+            self.accumulator -= time_step;
+            steps += 1;
+        }
+
+        steps
+    }
+
+    /// Advances the world by exactly `dt` milliseconds.
+    pub fn step(&mut self, dt: f32) {
+        self.tick += 1;
+        let clock = self.clock();
+
+        update(
+            &mut self.entities,
+            Food {
+                plants: &mut self.plants,
+                corpses: &mut self.corpses,
+            },
+            &self.genome_layout,
+            &self.config,
+            clock.sense_factor(&self.config),
+            dt,
+            &mut self.rng,
+        );
+
+        // Increase the fertility of the land where the dead entities died, or leave corpses
+        // that will.
+        for entity in self.entities.iter() {
+            if entity.energy > 0.0 {
+                continue;
+            }
+
+            if self.config.corpse_energy > 0.0 && !entity.eaten() {
+                self.corpses.push(Corpse::new(entity, &self.config));
+                continue;
+            }
+
+            if let Some(block) = self.terrain.block_at_mut(entity.position()) {
+                if block.biome() != Biome::Water {
+                    block.fertility += self.config.death_fertility;
+                }
+            }
+        }
+
+        // Corpses rot into the land under them.
+        for corpse in self.corpses.iter_mut() {
+            let fertility = corpse.decay(&self.config);
+
+            if let Some(block) = self.terrain.block_at_mut(corpse.position()) {
+                if block.biome() != Biome::Water {
+                    block.fertility += fertility;
+                }
+            }
+        }
+        let config = &self.config;
+        self.corpses.retain(|corpse| !corpse.is_gone(config));
+
+        // Plants grow on fertile land, and give some back when they die of old age.
+        let growth_factor = clock.growth_factor(&self.config);
+        for plant in self.plants.iter_mut().filter(|plant| plant.size() > 0.0) {
+            let mut block = self.terrain.block_at_mut(plant.position());
+            let fertility = block.as_ref().map_or(0.0, |block| block.fertility);
+
+            if plant.live(fertility, growth_factor, &self.config) {
+                if let Some(block) = block.as_mut().filter(|b| b.biome() != Biome::Water) {
+                    block.fertility += self.config.plant_death_fertility;
+                }
+            }
+        }
+
+        self.terrain.update(&self.config, &clock);
+
+        for plant in self.plants.iter().filter(|plant| plant.size() <= 0.0) {
+            self.plant_ids.release(plant.id());
+        }
+        for entity in self.entities.iter().filter(|entity| entity.energy <= 0.0) {
+            self.entity_ids.release(entity.id());
+            self.lineage.record_death(entity.id(), self.tick);
+        }
+
+        self.plants.retain(|plant| plant.size() > 0.0);
+        self.entities.retain(|entity| entity.energy > 0.0);
+        // Entities that were eaten but ate enough afterwards in the same step live on, and should
+        // leave a corpse if they starve later.
+        for entity in self.entities.iter_mut() {
+            entity.eaten = false;
+        }
+        self.terrain.count_plants(&self.plants);
+
+        self.grow_plants(&clock);
+        self.register_newcomers();
+
+        if self.tick.is_multiple_of(self.config.species_interval) {
+            self.cluster_species();
+        }
+
+        self.stats.record(self.tick, &self.entities, &self.plants);
+    }
+
+    /// Checks the saved config and rebuilds what is not saved in a snapshot.
+    pub(crate) fn restore(&mut self) -> Result<(), ConfigError> {
+        self.config.validate()?;
+
+        self.genome_layout = GenomeLayout::new(&self.config);
+        for entity in self.entities.iter_mut() {
+            entity.genome_mut().conform(&self.genome_layout);
+        }
+        self.plant_layout = GenomeLayout::for_plants(&self.config);
+        for plant in self.plants.iter_mut() {
+            plant.genome_mut().conform(&self.plant_layout);
+        }
+
+        self.register_newcomers();
+
+        Ok(())
+    }
+
+    /// Gives an id to the entities and plants born since the last call, records the new entities
+    /// in the lineage and maps every id to the current index of its holder.
+    pub(crate) fn register_newcomers(&mut self) {
+        self.entity_ids
+            .index(self.entities.iter_mut().map(|entity| &mut entity.id));
+        self.plant_ids
+            .index(self.plants.iter_mut().map(|plant| &mut plant.id));
+
+        for entity in self.entities.iter() {
+            self.lineage.record_birth(entity, self.tick);
+        }
+    }
+
+    // Generate more plants
+    // Plants are more likely to seed on fertile land, which depends on the terrain: moist
+    // blocks of noise terrain, or the center of radial terrain. Nothing seeds on water.
+    // Growth is logistic: it slows down as a block fills up to its carrying capacity.
+    // Seasons speed it up or slow it down.
+    fn grow_plants(&mut self, clock: &Clock) {
+        let growth_rate = self.config.plant_growth_rate * clock.growth_factor(&self.config);
+
+        let mut new_plants: Vec<Plant> = vec![];
+
+        for plant in self.plants.iter() {
+            // If the plant is in a fertile area the probability of it growing is higher
+            let block = match self.terrain.block_at(plant.position()) {
+                Some(block) => block,
+                None => continue,
+            };
+
+            let probability_of_growth = block.fertility * growth_rate * block.free_capacity();
+
+            if self.rng.gen::<f32>() >= probability_of_growth {
+                continue;
+            }
+
+            for _ in 0..plant.seed_count() {
+                let new_plant_size = self.rng.gen::<f32>() * 0.1 + 0.1;
+                let random_vec = random_direction(&mut self.rng);
+
+                let new_plant_position = plant.position()
+                    + random_vec * (plant.size() + new_plant_size) * plant.seed_distance();
+
+                // Reduce fertility of the block
+                if let Some(block) = self.terrain.block_at_mut(plant.position()) {
+                    block.fertility -= self.config.plant_fertility_cost;
+
+                    if block.fertility < 0.0 {
+                        block.fertility = 0.0;
+                    }
+                }
+
+                self.terrain.count_new_plant(new_plant_position);
+
+                let genome = plant.genome().mutated(&self.plant_layout, &mut self.rng);
+                new_plants.push(Plant::with_genome(
+                    genome,
+                    new_plant_position,
+                    new_plant_size,
+                ));
+            }
+        }
+
+        self.plants.append(&mut new_plants);
+    }
+}
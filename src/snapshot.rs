@@ -0,0 +1,199 @@
+//! Saving and loading the whole world.
+//!
+//! A binary snapshot is the `MAGIC` bytes, the format version as a little endian `u32` and the
+//! world encoded as MessagePack. A JSON snapshot is `{ "version": .., "simulation": .. }`.
+//! Both encodings keep field names, so a field added in a later version only needs a
+//! `#[serde(default)]` for saves from older builds to keep loading. Changes that move data
+//! around are handled by `migrate`.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::ConfigError;
+use crate::genome::BUILT_IN_GENES;
+use crate::simulation::Simulation;
+use crate::terrain::{radial_moisture, Biome};
+
+/// Bump this only when the saved data changes in a way that `migrate` has to handle, and keep a
+/// snapshot of the previous version in `tests/fixtures` to test the migration. Fields that only
+/// need a `#[serde(default)]` don't change the version.
+pub const SNAPSHOT_VERSION: u32 = 11;
+
+/// The first version that saves the traits of an entity in a genome.
+const GENOME_VERSION: u32 = 8;
+/// The first version that saves the elevation, moisture and biome of terrain blocks.
+const BIOME_VERSION: u32 = 11;
+
+const MAGIC: &[u8; 4] = b"EVOL";
+const HEADER_LEN: usize = MAGIC.len() + 4;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The bytes do not start with the snapshot header.
+    NotASnapshot,
+    /// The snapshot was written by a newer build.
+    UnsupportedVersion(u32),
+    /// The payload could not be decoded.
+    Decode(String),
+    /// The saved config is out of the valid ranges, see `SimConfig::validate`.
+    Config(ConfigError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::NotASnapshot => write!(f, "not a snapshot"),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot version {} is newer than the supported version {}",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::Decode(message) => write!(f, "corrupted snapshot: {}", message),
+            SnapshotError::Config(error) => write!(f, "snapshot with an {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    simulation: &'a Simulation,
+}
+
+fn check_version(version: u32) -> Result<(), SnapshotError> {
+    if version > SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+
+    Ok(())
+}
+
+/// Rewrites a simulation saved by an older build into the current shape.
+fn migrate(version: u32, simulation: &mut Value) {
+    if version < GENOME_VERSION {
+        // The built-in traits used to be fields of the entity and of its lineage record.
+        for field in ["entities", "lineage"].iter() {
+            let records = match simulation.get_mut(*field).and_then(Value::as_array_mut) {
+                Some(records) => records,
+                None => continue,
+            };
+
+            for record in records.iter_mut().filter_map(Value::as_object_mut) {
+                let genome = BUILT_IN_GENES
+                    .iter()
+                    .map(|name| record.remove(*name).unwrap_or(Value::Null))
+                    .collect();
+                record.insert("genome".to_string(), Value::Array(genome));
+            }
+        }
+    }
+
+    if version < BIOME_VERSION {
+        // Every terrain was radial: flat land, as moist as it was fertile at first.
+        let rows = match simulation
+            .pointer_mut("/terrain/blocks")
+            .and_then(Value::as_array_mut)
+        {
+            Some(rows) => rows,
+            None => return,
+        };
+
+        for (x, row) in rows.iter_mut().enumerate() {
+            let blocks = match row.as_array_mut() {
+                Some(blocks) => blocks,
+                None => continue,
+            };
+
+            for (y, block) in blocks.iter_mut().enumerate() {
+                let block = match block.as_object_mut() {
+                    Some(block) => block,
+                    None => continue,
+                };
+
+                let moisture = radial_moisture(x, y);
+                let biome = Biome::classify(1.0, moisture, 0.0);
+                block.insert("elevation".to_string(), Value::from(1.0));
+                block.insert("moisture".to_string(), Value::from(moisture));
+                block.insert(
+                    "biome".to_string(),
+                    serde_json::to_value(biome).expect("a biome can always be encoded"),
+                );
+            }
+        }
+    }
+}
+
+impl Simulation {
+    /// Encodes the whole world, including the RNG state, as a binary snapshot.
+    pub fn save(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+
+        rmp_serde::encode::write_named(&mut bytes, self)
+            .expect("a simulation can always be encoded");
+
+        bytes
+    }
+
+    pub fn load(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+
+        let mut version = [0; 4];
+        version.copy_from_slice(&bytes[MAGIC.len()..HEADER_LEN]);
+        let version = u32::from_le_bytes(version);
+        check_version(version)?;
+
+        let decode_error =
+            |error: rmp_serde::decode::Error| SnapshotError::Decode(error.to_string());
+        let payload = &bytes[HEADER_LEN..];
+
+        // Current snapshots are decoded directly, `Value` can't hold NaNs.
+        let mut simulation = if version < SNAPSHOT_VERSION {
+            let mut value: Value = rmp_serde::from_slice(payload).map_err(decode_error)?;
+            migrate(version, &mut value);
+            Simulation::deserialize(value)
+                .map_err(|error| SnapshotError::Decode(error.to_string()))?
+        } else {
+            rmp_serde::from_slice::<Simulation>(payload).map_err(decode_error)?
+        };
+        simulation.restore().map_err(SnapshotError::Config)?;
+
+        Ok(simulation)
+    }
+
+    /// Encodes the whole world, including the RNG state, as a JSON snapshot.
+    pub fn save_json(&self) -> String {
+        serde_json::to_string(&SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            simulation: self,
+        })
+        .expect("a simulation can always be encoded")
+    }
+
+    pub fn load_json(json: &str) -> Result<Self, SnapshotError> {
+        let decode_error = |error: serde_json::Error| SnapshotError::Decode(error.to_string());
+
+        let mut snapshot: Value = serde_json::from_str(json).map_err(decode_error)?;
+        let version = snapshot
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or(SnapshotError::NotASnapshot)?;
+        let version = u32::try_from(version).unwrap_or(u32::MAX);
+        check_version(version)?;
+
+        let mut simulation = snapshot["simulation"].take();
+        migrate(version, &mut simulation);
+        let mut simulation = Simulation::deserialize(simulation).map_err(decode_error)?;
+        simulation.restore().map_err(SnapshotError::Config)?;
+
+        Ok(simulation)
+    }
+}
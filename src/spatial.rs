@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use glam::Vec2;
+
+/// A uniform grid over the (unbounded) world used to find the closest entity or plant without
+/// scanning all of them.
+///
+/// The grid only stores indices into the caller's `Vec`, the caller measures distances itself.
+/// `closest` returns exactly what a linear scan in index order keeping the first strictly
+/// smaller distance would return.
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    // Bounding box of every cell that has ever held an item, in cell coordinates.
+    min_cell: (i32, i32),
+    max_cell: (i32, i32),
+    // The biggest `size` of any inserted item, used to bound the distance of unvisited cells.
+    max_size: f32,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            min_cell: (i32::MAX, i32::MAX),
+            max_cell: (i32::MIN, i32::MIN),
+            max_size: 0.0,
+        }
+    }
+
+    fn cell_of(&self, position: Vec2) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Adds the item `index` at `position`.
+    ///
+    /// Items at non finite positions are never the closest to anything, so they are not stored.
+    pub fn insert(&mut self, index: usize, position: Vec2, size: f32) {
+        if !position.is_finite() {
+            return;
+        }
+
+        let cell = self.cell_of(position);
+        self.cells.entry(cell).or_default().push(index);
+
+        self.min_cell = (self.min_cell.0.min(cell.0), self.min_cell.1.min(cell.1));
+        self.max_cell = (self.max_cell.0.max(cell.0), self.max_cell.1.max(cell.1));
+        self.max_size = self.max_size.max(size);
+    }
+
+    /// Moves the item `index` from `from` to `to`.
+    pub fn relocate(&mut self, index: usize, from: Vec2, to: Vec2, size: f32) {
+        if from.is_finite() && to.is_finite() && self.cell_of(from) == self.cell_of(to) {
+            return;
+        }
+
+        if from.is_finite() {
+            let cell = self.cell_of(from);
+            if let Some(indices) = self.cells.get_mut(&cell) {
+                if let Some(slot) = indices.iter().position(|&i| i == index) {
+                    indices.swap_remove(slot);
+                }
+            }
+        }
+
+        self.insert(index, to, size);
+    }
+
+    /// Finds the item with the smallest `distance` that is below `max_distance`.
+    ///
+    /// `distance(index)` returns the distance from the query to an item, or `None` to skip it.
+    /// `size` is the size of the querying object: distances are measured between edges, so an
+    /// item can be up to `size + max_size` closer than its center. Ties go to the lowest index.
+    pub fn closest(
+        &self,
+        position: Vec2,
+        size: f32,
+        max_distance: f32,
+        mut distance: impl FnMut(usize) -> Option<f32>,
+    ) -> Option<(usize, f32)> {
+        if !position.is_finite() || self.cells.is_empty() {
+            return None;
+        }
+
+        let (cx, cy) = self.cell_of(position);
+        let reach = size + self.max_size;
+        let mut best: Option<(usize, f32)> = None;
+
+        let mut ring: i32 = 0;
+        loop {
+            // Every cell in this ring is at least `ring - 1` whole cells away from the query. One
+            // more ring of slack absorbs rounding in the cell assignment.
+            let lower_bound = (ring - 2).max(0) as f32 * self.cell_size - reach;
+
+            if lower_bound >= max_distance {
+                break;
+            }
+
+            if let Some((_, best_distance)) = best {
+                if lower_bound > best_distance {
+                    break;
+                }
+            }
+
+            let x_start = cx.saturating_sub(ring).max(self.min_cell.0);
+            let x_end = cx.saturating_add(ring).min(self.max_cell.0);
+
+            for x in x_start..=x_end {
+                let on_vertical_edge = (x - cx).abs() == ring;
+
+                let mut visit = |y: i32| {
+                    if let Some(indices) = self.cells.get(&(x, y)) {
+                        for &index in indices {
+                            let d = match distance(index) {
+                                Some(d) => d,
+                                None => continue,
+                            };
+
+                            if d >= max_distance || d.is_nan() {
+                                continue;
+                            }
+
+                            best = match best {
+                                Some((best_index, best_distance))
+                                    if d > best_distance
+                                        || (d == best_distance && best_index < index) =>
+                                {
+                                    Some((best_index, best_distance))
+                                }
+                                _ => Some((index, d)),
+                            };
+                        }
+                    }
+                };
+
+                if on_vertical_edge {
+                    let y_start = cy.saturating_sub(ring).max(self.min_cell.1);
+                    let y_end = cy.saturating_add(ring).min(self.max_cell.1);
+                    for y in y_start..=y_end {
+                        visit(y);
+                    }
+                } else {
+                    visit(cy - ring);
+                    visit(cy + ring);
+                }
+            }
+
+            // Stop once the rings visited so far cover every occupied cell.
+            if cx.saturating_sub(ring) <= self.min_cell.0
+                && cx.saturating_add(ring) >= self.max_cell.0
+                && cy.saturating_sub(ring) <= self.min_cell.1
+                && cy.saturating_add(ring) >= self.max_cell.1
+            {
+                break;
+            }
+
+            ring += 1;
+        }
+
+        best
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::entity::Entity;
+use crate::genome::{Genome, GenomeLayout};
+
+pub type SpeciesId = u32;
+
+/// A cluster of entities with similar genomes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Species {
+    id: SpeciesId,
+    birth_tick: u64,
+    population: usize,
+    centroid: Genome,
+}
+
+impl Species {
+    pub fn id(&self) -> SpeciesId {
+        self.id
+    }
+
+    /// The tick of the clustering that found the species.
+    pub fn birth_tick(&self) -> u64 {
+        self.birth_tick
+    }
+
+    /// Number of members as of the last clustering.
+    pub fn population(&self) -> usize {
+        self.population
+    }
+
+    /// The mean genome of the members as of the last clustering.
+    pub fn centroid(&self) -> &Genome {
+        &self.centroid
+    }
+}
+
+/// Groups entities into species, keeping the ids of species from one clustering to the next.
+///
+/// Every entity joins the species with the closest centroid if it is within `max_distance`
+/// (see `Genome::distance`), otherwise it founds a new one that the next entities can join.
+/// Species from the previous clustering are tried first, so a species keeps its id for as long
+/// as it has members around its center, and goes extinct when it has none.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpeciesTracker {
+    species: Vec<Species>,
+    next_id: SpeciesId,
+}
+
+impl SpeciesTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The living species, oldest first.
+    pub fn species(&self) -> &[Species] {
+        &self.species
+    }
+
+    pub fn get(&self, id: SpeciesId) -> Option<&Species> {
+        self.species.iter().find(|species| species.id == id)
+    }
+
+    /// The living species as a JSON array of `{ id, birth_tick, population, centroid }`, where
+    /// `centroid` maps the name of every gene of `layout` to its mean.
+    pub fn to_json(&self, layout: &GenomeLayout) -> String {
+        let species: Vec<Value> = self
+            .species
+            .iter()
+            .map(|species| {
+                let centroid: Map<String, Value> = layout
+                    .genes()
+                    .iter()
+                    .zip(species.centroid.values())
+                    .map(|(gene, value)| (gene.name.clone(), json!(value)))
+                    .collect();
+
+                json!({
+                    "id": species.id,
+                    "birth_tick": species.birth_tick,
+                    "population": species.population,
+                    "centroid": centroid,
+                })
+            })
+            .collect();
+
+        serde_json::to_string(&species).expect("species can always be encoded")
+    }
+
+    /// Assigns every entity to a species and updates the species to match.
+    pub fn cluster(
+        &mut self,
+        entities: &mut [Entity],
+        layout: &GenomeLayout,
+        max_distance: f32,
+        tick: u64,
+    ) {
+        let mut species = std::mem::take(&mut self.species);
+        let mut sums: Vec<Vec<f64>> = vec![vec![0.0; layout.len()]; species.len()];
+        let mut populations = vec![0; species.len()];
+
+        for entity in entities.iter_mut() {
+            let closest = species
+                .iter()
+                .map(|species| entity.genome().distance(&species.centroid, layout))
+                .enumerate()
+                .filter(|(_, distance)| *distance <= max_distance)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            let k = match closest {
+                Some((k, _)) => k,
+                None => {
+                    species.push(Species {
+                        id: self.next_id,
+                        birth_tick: tick,
+                        population: 0,
+                        centroid: entity.genome().clone(),
+                    });
+                    sums.push(vec![0.0; layout.len()]);
+                    populations.push(0);
+                    self.next_id += 1;
+                    species.len() - 1
+                }
+            };
+
+            entity.species = Some(species[k].id);
+            populations[k] += 1;
+            for (sum, value) in sums[k].iter_mut().zip(entity.genome().values()) {
+                *sum += *value as f64;
+            }
+        }
+
+        self.species = species
+            .into_iter()
+            .zip(sums)
+            .zip(populations)
+            .filter(|(_, population)| *population > 0)
+            .map(|((species, sums), population)| Species {
+                population,
+                centroid: Genome::new(
+                    sums.iter()
+                        .map(|sum| (sum / population as f64) as f32)
+                        .collect(),
+                ),
+                ..species
+            })
+            .collect();
+    }
+}
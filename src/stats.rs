@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::entity::{Entity, Plant};
+
+/// Number of equal width histogram bins over [0, 1], the range of every heritable trait.
+pub const HISTOGRAM_BINS: usize = 10;
+
+/// How many ticks `Stats` keeps by default.
+pub const DEFAULT_STATS_CAPACITY: usize = 4096;
+
+/// The heritable traits that statistics are collected for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trait {
+    Size,
+    SenseDistance,
+    VelocityMag,
+}
+
+impl Trait {
+    pub const ALL: [Trait; 3] = [Trait::Size, Trait::SenseDistance, Trait::VelocityMag];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Trait::Size => "size",
+            Trait::SenseDistance => "sense_distance",
+            Trait::VelocityMag => "velocity_mag",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Trait::ALL.iter().copied().find(|t| t.name() == name)
+    }
+
+    fn value(self, entity: &Entity) -> f32 {
+        match self {
+            Trait::Size => entity.size(),
+            Trait::SenseDistance => entity.sense_distance(),
+            Trait::VelocityMag => entity.velocity_mag(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TraitStats {
+    pub mean: f32,
+    pub variance: f32,
+    pub histogram: [u32; HISTOGRAM_BINS],
+}
+
+impl TraitStats {
+    fn collect(entities: &[Entity], t: Trait) -> Self {
+        let mut stats = TraitStats::default();
+
+        if entities.is_empty() {
+            return stats;
+        }
+
+        let count = entities.len() as f32;
+        stats.mean = entities.iter().map(|entity| t.value(entity)).sum::<f32>() / count;
+        stats.variance = entities
+            .iter()
+            .map(|entity| (t.value(entity) - stats.mean).powi(2))
+            .sum::<f32>()
+            / count;
+
+        for entity in entities {
+            let bin = (t.value(entity) * HISTOGRAM_BINS as f32) as usize;
+            stats.histogram[bin.min(HISTOGRAM_BINS - 1)] += 1;
+        }
+
+        stats
+    }
+}
+
+/// Everything recorded about the world at the end of one tick.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Sample {
+    pub tick: u64,
+    pub population: u32,
+    pub plants: u32,
+    pub total_energy: f32,
+    pub traits: [TraitStats; 3],
+}
+
+impl Sample {
+    pub fn trait_stats(&self, t: Trait) -> &TraitStats {
+        &self.traits[t as usize]
+    }
+
+    /// This sample as a row of `Stats::to_csv`, newline included.
+    pub fn to_csv_row(&self) -> String {
+        let mut csv = format!(
+            "{},{},{},{}",
+            self.tick, self.population, self.plants, self.total_energy
+        );
+
+        for t in Trait::ALL.iter() {
+            let stats = self.trait_stats(*t);
+            write!(csv, ",{},{}", stats.mean, stats.variance).unwrap();
+            for count in stats.histogram.iter() {
+                write!(csv, ",{}", count).unwrap();
+            }
+        }
+        csv.push('\n');
+
+        csv
+    }
+}
+
+/// A bounded ring buffer of per-tick samples, oldest first.
+#[derive(Serialize, Deserialize)]
+pub struct Stats {
+    capacity: usize,
+    samples: VecDeque<Sample>,
+}
+
+impl Stats {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, entities: &[Entity], plants: &[Plant]) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(Sample {
+            tick,
+            population: entities.len() as u32,
+            plants: plants.len() as u32,
+            total_energy: entities.iter().map(|entity| entity.energy).sum(),
+            traits: Trait::ALL.map(|t| TraitStats::collect(entities, t)),
+        });
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// One value per sample, oldest first.
+    pub fn series<T>(&self, value: impl Fn(&Sample) -> T) -> Vec<T> {
+        self.samples.iter().map(value).collect()
+    }
+
+    /// The histograms of `t` for every sample, flattened row by row.
+    pub fn histograms(&self, t: Trait) -> Vec<u32> {
+        self.samples
+            .iter()
+            .flat_map(|sample| sample.trait_stats(t).histogram.iter().copied())
+            .collect()
+    }
+
+    /// One row per sample, with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = Stats::csv_header();
+
+        for sample in self.samples.iter() {
+            csv.push_str(&sample.to_csv_row());
+        }
+
+        csv
+    }
+
+    /// The header row of `to_csv`, newline included.
+    pub fn csv_header() -> String {
+        let mut csv = String::from("tick,population,plants,total_energy");
+        for t in Trait::ALL.iter() {
+            write!(csv, ",{0}_mean,{0}_variance", t.name()).unwrap();
+            for bin in 0..HISTOGRAM_BINS {
+                write!(csv, ",{}_bin_{}", t.name(), bin).unwrap();
+            }
+        }
+        csv.push('\n');
+
+        csv
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATS_CAPACITY)
+    }
+}
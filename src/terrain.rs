@@ -0,0 +1,340 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::Clock;
+use crate::config::SimConfig;
+use crate::entity::Plant;
+use crate::world::{block_to_world, world_to_block, GRID_SIZE, WORLD_HALF_EXTENT};
+
+/// Fertility never goes above this, nor below 0.
+pub static MAX_FERTILITY: f32 = 1.0;
+
+/// Land drier than this is desert.
+static DESERT_MOISTURE: f32 = 0.35;
+/// Land wetter than this is forest.
+static FOREST_MOISTURE: f32 = 0.6;
+
+/// How the terrain of a new world is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerrainKind {
+    /// Flat land whose fertility falls off with the distance to the center.
+    #[default]
+    Radial,
+    /// Hills, lakes and dry lands from fractal noise, different for every seed.
+    Noise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum Biome {
+    /// Under the water level. Plants can't grow in it.
+    Water = 0,
+    Desert = 1,
+    #[default]
+    Grassland = 2,
+    Forest = 3,
+}
+
+impl Biome {
+    /// The biome of land at `elevation` with `moisture`, both in [0, 1].
+    pub fn classify(elevation: f32, moisture: f32, water_level: f32) -> Self {
+        if elevation < water_level {
+            Biome::Water
+        } else if moisture < DESERT_MOISTURE {
+            Biome::Desert
+        } else if moisture < FOREST_MOISTURE {
+            Biome::Grassland
+        } else {
+            Biome::Forest
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Biome::Water => "water",
+            Biome::Desert => "desert",
+            Biome::Grassland => "grassland",
+            Biome::Forest => "forest",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TerrainBlock {
+    number_of_plants: u32,
+    max_carrying_capacity: u32,
+    pub fertility: f32,
+    elevation: f32,
+    moisture: f32,
+    biome: Biome,
+}
+
+impl TerrainBlock {
+    /// Plants currently growing inside this block.
+    pub fn number_of_plants(&self) -> u32 {
+        self.number_of_plants
+    }
+
+    pub fn max_carrying_capacity(&self) -> u32 {
+        self.max_carrying_capacity
+    }
+
+    /// How much room is left for new plants: 1 for an empty block, 0 for a full one.
+    pub fn free_capacity(&self) -> f32 {
+        if self.max_carrying_capacity == 0 {
+            return 0.0;
+        }
+
+        let occupancy = self.number_of_plants as f32 / self.max_carrying_capacity as f32;
+        (1.0 - occupancy).max(0.0)
+    }
+
+    /// Height of the land, in [0, 1].
+    pub fn elevation(&self) -> f32 {
+        self.elevation
+    }
+
+    /// How wet the land is, in [0, 1].
+    pub fn moisture(&self) -> f32 {
+        self.moisture
+    }
+
+    /// The fertility the block starts with and regenerates towards: its moisture, or 0 for
+    /// water.
+    pub fn base_fertility(&self) -> f32 {
+        match self.biome {
+            Biome::Water => 0.0,
+            _ => self.moisture,
+        }
+    }
+
+    pub fn biome(&self) -> Biome {
+        self.biome
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Terrain {
+    blocks: Vec<Vec<TerrainBlock>>,
+}
+
+impl Terrain {
+    /// The `TerrainKind::Radial` terrain.
+    pub fn new(max_carrying_capacity: u32) -> Self {
+        Self::from_fields(max_carrying_capacity, 0.0, |x, y| {
+            (1.0, radial_moisture(x, y))
+        })
+    }
+
+    /// The terrain of a world created with `config`. `seed` only matters to noise terrain.
+    pub fn generate(config: &SimConfig, seed: u64) -> Self {
+        match config.terrain {
+            TerrainKind::Radial => Self::new(config.plant_carrying_capacity),
+            TerrainKind::Noise => {
+                // Different seeds for the two fields, so that hills aren't always wet.
+                let moisture_seed = seed ^ 0x9e37_79b9_7f4a_7c15;
+
+                Self::from_fields(
+                    config.plant_carrying_capacity,
+                    config.water_level,
+                    |x, y| {
+                        let x = x as f32 / config.terrain_scale;
+                        let y = y as f32 / config.terrain_scale;
+                        (
+                            fractal_noise(seed, x, y, config.terrain_octaves),
+                            fractal_noise(moisture_seed, x, y, config.terrain_octaves),
+                        )
+                    },
+                )
+            }
+        }
+    }
+
+    // Builds the blocks from the elevation and the moisture of every grid coordinate.
+    fn from_fields(
+        max_carrying_capacity: u32,
+        water_level: f32,
+        fields: impl Fn(usize, usize) -> (f32, f32),
+    ) -> Self {
+        let mut blocks: Vec<Vec<TerrainBlock>> = vec![];
+
+        for x in 0..GRID_SIZE {
+            let mut row: Vec<TerrainBlock> = vec![];
+
+            for y in 0..GRID_SIZE {
+                let (elevation, moisture) = fields(x, y);
+                let mut block = TerrainBlock {
+                    number_of_plants: 0,
+                    fertility: 0.0,
+                    max_carrying_capacity,
+                    elevation,
+                    moisture,
+                    biome: Biome::classify(elevation, moisture, water_level),
+                };
+                block.fertility = block.base_fertility();
+
+                row.push(block);
+            }
+
+            blocks.push(row);
+        }
+
+        Self { blocks }
+    }
+
+    pub fn blocks(&self) -> &Vec<Vec<TerrainBlock>> {
+        &self.blocks
+    }
+
+    /// Moves the fertility of every block one tick forward: it spreads to the neighboring land
+    /// (`SimConfig::fertility_diffusion`), regenerates towards the base fertility of the block
+    /// (`SimConfig::fertility_regeneration`, scaled by the season of `clock`) and is clamped to
+    /// [0, `MAX_FERTILITY`]. Water stays barren.
+    pub fn update(&mut self, config: &SimConfig, clock: &Clock) {
+        let before: Vec<Vec<f32>> = self
+            .blocks
+            .iter()
+            .map(|row| row.iter().map(|block| block.fertility).collect())
+            .collect();
+        let is_land = |x: usize, y: usize| self.blocks[x][y].biome != Biome::Water;
+
+        let mut after = before.clone();
+        for x in 0..GRID_SIZE {
+            for y in 0..GRID_SIZE {
+                if !is_land(x, y) {
+                    continue;
+                }
+
+                // Every pair of neighbors exchanges the same amount, so spreading keeps the
+                // total fertility.
+                let neighbors = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                for (nx, ny) in neighbors.iter().copied() {
+                    if nx < GRID_SIZE && ny < GRID_SIZE && is_land(nx, ny) {
+                        after[x][y] +=
+                            config.fertility_diffusion / 4.0 * (before[nx][ny] - before[x][y]);
+                    }
+                }
+            }
+        }
+
+        let regeneration =
+            (config.fertility_regeneration * clock.regeneration_factor(config)).min(1.0);
+        for (block, fertility) in self
+            .blocks
+            .iter_mut()
+            .flatten()
+            .zip(after.into_iter().flatten())
+        {
+            let base = block.base_fertility();
+            let fertility = fertility + regeneration * (base - fertility);
+
+            block.fertility = match block.biome {
+                Biome::Water => 0.0,
+                _ => fertility.clamp(0.0, MAX_FERTILITY),
+            };
+        }
+    }
+
+    /// Recounts the plants inside every block.
+    pub fn count_plants(&mut self, plants: &[Plant]) {
+        for block in self.blocks.iter_mut().flatten() {
+            block.number_of_plants = 0;
+        }
+
+        for plant in plants {
+            self.count_new_plant(plant.position());
+        }
+    }
+
+    /// Counts one more plant in the block under `position`.
+    pub fn count_new_plant(&mut self, position: Vec2) {
+        if let Some(block) = self.block_at_mut(position) {
+            block.number_of_plants += 1;
+        }
+    }
+
+    /// Returns the block under `position`, or `None` if the position is outside of the grid.
+    pub fn block_at(&self, position: Vec2) -> Option<&TerrainBlock> {
+        let (x, y) = world_to_block(position)?;
+        Some(&self.blocks[x][y])
+    }
+
+    pub fn block_at_mut(&mut self, position: Vec2) -> Option<&mut TerrainBlock> {
+        let (x, y) = world_to_block(position)?;
+        Some(&mut self.blocks[x][y])
+    }
+}
+
+/// The moisture of the block at grid coordinates `x`, `y` in radial terrain: places closer to
+/// the center are wetter, so more fertile.
+pub(crate) fn radial_moisture(x: usize, y: usize) -> f32 {
+    let max_distance_from_center = Vec2::new(WORLD_HALF_EXTENT, WORLD_HALF_EXTENT).length();
+    let distance_from_center = block_to_world(x, y).length();
+
+    1.0 - (distance_from_center / max_distance_from_center)
+}
+
+/// Fractal value noise in [0, 1]: `octaves` layers of smoothly interpolated random values on an
+/// integer lattice, each twice as fine and half as strong as the previous one.
+pub fn fractal_noise(seed: u64, x: f32, y: f32, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut total_amplitude = 0.0;
+    let mut frequency = 1.0;
+
+    for octave in 0..octaves {
+        sum += amplitude
+            * value_noise(
+                seed.wrapping_add(octave as u64),
+                x * frequency,
+                y * frequency,
+            );
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    if total_amplitude == 0.0 {
+        return 0.0;
+    }
+
+    sum / total_amplitude
+}
+
+fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (smoothstep(x - x0), smoothstep(y - y0));
+    let (x0, y0) = (x0 as i64, y0 as i64);
+
+    let top = lerp(lattice(seed, x0, y0), lattice(seed, x0 + 1, y0), tx);
+    let bottom = lerp(lattice(seed, x0, y0 + 1), lattice(seed, x0 + 1, y0 + 1), tx);
+
+    lerp(top, bottom, ty)
+}
+
+// A random value in [0, 1) for every point of the lattice, from a SplitMix64 style hash.
+fn lattice(seed: u64, x: i64, y: i64) -> f32 {
+    let mut hash = seed
+        .wrapping_add((x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+        .wrapping_add((y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f));
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^= hash >> 31;
+
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
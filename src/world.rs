@@ -0,0 +1,50 @@
+//! The world coordinate system.
+//!
+//! Positions and sizes of entities and plants are in world units. The terrain is a
+//! `GRID_SIZE` x `GRID_SIZE` grid of square blocks of `BLOCK_SIZE` world units centered on the
+//! origin, so it covers `[-WORLD_HALF_EXTENT, WORLD_HALF_EXTENT)` on both axes. The canvas draws
+//! `PIXELS_PER_UNIT` pixels per world unit before the camera transform is applied.
+
+use glam::Vec2;
+
+pub static GRID_SIZE: usize = 50;
+/// Side of a terrain block, in world units.
+pub static BLOCK_SIZE: f32 = 4.0;
+/// Distance from the origin to the edge of the terrain, in world units.
+pub static WORLD_HALF_EXTENT: f32 = GRID_SIZE as f32 * BLOCK_SIZE / 2.0;
+/// Canvas pixels per world unit at a zoom of 1.
+pub static PIXELS_PER_UNIT: f32 = 50.0;
+
+/// Grid coordinates of the terrain block that contains `position`, or `None` outside of the
+/// terrain.
+pub fn world_to_block(position: Vec2) -> Option<(usize, usize)> {
+    let x = ((position.x + WORLD_HALF_EXTENT) / BLOCK_SIZE).floor();
+    let y = ((position.y + WORLD_HALF_EXTENT) / BLOCK_SIZE).floor();
+
+    // Also rejects NaN.
+    let in_grid = |value: f32| value >= 0.0 && value < GRID_SIZE as f32;
+    if !(in_grid(x) && in_grid(y)) {
+        return None;
+    }
+
+    Some((x as usize, y as usize))
+}
+
+/// Center of the terrain block at grid coordinates `x`, `y`.
+pub fn block_to_world(x: usize, y: usize) -> Vec2 {
+    Vec2::new(
+        (x as f32 + 0.5) * BLOCK_SIZE - WORLD_HALF_EXTENT,
+        (y as f32 + 0.5) * BLOCK_SIZE - WORLD_HALF_EXTENT,
+    )
+}
+
+/// Canvas coordinates of `position`, before the camera transform.
+pub fn world_to_screen(position: Vec2) -> Vec2 {
+    position * PIXELS_PER_UNIT
+}
+
+/// The world position under the canvas point `screen` (e.g. the mouse), given the camera
+/// translation and scale.
+pub fn screen_to_world(screen: Vec2, camera: Vec2, scale: f32) -> Vec2 {
+    (screen - camera) / scale / PIXELS_PER_UNIT
+}
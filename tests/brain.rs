@@ -0,0 +1,131 @@
+//! Neural brains and the entities they drive.
+
+mod common;
+
+use common::{add_entity, DT};
+use game::{
+    brain_weights, think, Behavior, Plant, Senses, SimConfig, Simulation, BRAIN_INPUTS,
+    BRAIN_OUTPUTS,
+};
+use glam::Vec2;
+
+fn neural_config() -> SimConfig {
+    SimConfig {
+        behavior: Behavior::Neural,
+        brain_hidden_neurons: 4,
+        ..SimConfig::default()
+    }
+}
+
+#[test]
+fn counts_weights_and_biases() {
+    assert_eq!(
+        brain_weights(4),
+        (BRAIN_INPUTS + 1) * 4 + (4 + 1) * BRAIN_OUTPUTS
+    );
+}
+
+#[test]
+fn a_blank_brain_stands_still_at_half_speed() {
+    let decision = think(&vec![0.0; brain_weights(3)], 3, &Senses::default());
+
+    assert_eq!(decision.steering, Vec2::ZERO);
+    assert_eq!(decision.speed, 0.5);
+}
+
+#[test]
+fn output_biases_steer() {
+    let hidden = 2;
+    let mut weights = vec![0.0; brain_weights(hidden)];
+    let outputs = (BRAIN_INPUTS + 1) * hidden;
+    // The bias of every output neuron.
+    weights[outputs] = 1.0;
+    weights[outputs + hidden + 1] = -1.0;
+    weights[outputs + 2 * (hidden + 1)] = 10.0;
+
+    let decision = think(&weights, hidden, &Senses::default());
+
+    assert_eq!(
+        decision.steering,
+        Vec2::new(1.0f32.tanh(), -(1.0f32.tanh()))
+    );
+    assert!(decision.speed > 0.99);
+}
+
+#[test]
+fn brains_are_part_of_the_genome() {
+    let simulation = Simulation::with_config(0, neural_config());
+    let layout = simulation.genome_layout();
+
+    assert_eq!(layout.brain(), Some(3..3 + brain_weights(4)));
+    assert_eq!(layout.len(), 3 + brain_weights(4));
+    assert_eq!(layout.genes()[3].name, "brain_0");
+    assert!(Simulation::new(0).genome_layout().brain().is_none());
+}
+
+#[test]
+fn children_inherit_their_brain() {
+    let config = SimConfig {
+        brain_mutation_rate: 0.0,
+        ..neural_config()
+    };
+    let mut simulation = Simulation::with_config(3, config);
+    simulation.populate(100, 1000);
+
+    let mut children = 0;
+    for _ in 0..300 {
+        simulation.step(DT);
+
+        let brain = simulation.genome_layout().brain().unwrap();
+        for entity in simulation.entities() {
+            let parent = match entity.parent_id() {
+                Some(parent) => simulation.lineage().get(parent).unwrap(),
+                None => continue,
+            };
+            assert_eq!(
+                entity.genome().slice(brain.clone()),
+                parent.genome.slice(brain.clone())
+            );
+            children += 1;
+        }
+    }
+
+    assert!(children > 0);
+}
+
+#[test]
+fn neural_entities_move_and_eat() {
+    let mut simulation = Simulation::with_config(0, neural_config());
+    let layout = simulation.genome_layout().clone();
+    let mut genes = vec![0.5, 0.5, 0.5];
+    genes.resize(layout.len(), 0.0);
+    // Full speed along x.
+    let outputs = 3 + (BRAIN_INPUTS + 1) * 4;
+    genes[outputs] = 4.0;
+    genes[outputs + 2 * 5] = 4.0;
+
+    add_entity(&mut simulation, &genes, Vec2::ZERO, 5000.0);
+    simulation.add_plant(Plant::new(Vec2::new(0.1, 0.0), 0.2));
+
+    simulation.step(DT);
+
+    let entity = &simulation.entities()[0];
+    assert!(simulation.plants().is_empty());
+    assert!(entity.energy > 5000.0);
+    assert!(entity.position().x > 0.0);
+    assert_eq!(entity.position().y, 0.0);
+}
+
+#[test]
+fn neural_worlds_are_reproducible() {
+    let run = || {
+        let mut simulation = Simulation::with_config(9, neural_config());
+        simulation.populate(50, 500);
+        for _ in 0..200 {
+            simulation.step(DT);
+        }
+        simulation.save()
+    };
+
+    assert_eq!(run(), run());
+}
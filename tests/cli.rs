@@ -0,0 +1,148 @@
+//! The headless `evol` binary.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use game::Simulation;
+
+fn out_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("evol-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn evol(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_evol"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn writes_stats_snapshots_and_lineage() {
+    let out = out_dir("run");
+    let config = out.with_extension("json");
+    fs::write(
+        &config,
+        r#"{ "initial_entities": 50, "initial_plants": 300 }"#,
+    )
+    .unwrap();
+
+    let output = evol(&[
+        "--ticks",
+        "120",
+        "--seed",
+        "3",
+        "--config",
+        config.to_str().unwrap(),
+        "--out",
+        out.to_str().unwrap(),
+        "--stats-every",
+        "30",
+        "--snapshot-every",
+        "60",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let stats = fs::read_to_string(out.join("stats.csv")).unwrap();
+    let ticks: Vec<&str> = stats
+        .lines()
+        .skip(1)
+        .map(|row| row.split(',').next().unwrap())
+        .collect();
+    assert_eq!(ticks, ["30", "60", "90", "120"]);
+
+    assert!(out.join("snapshots/tick_60.evol").exists());
+    assert!(out.join("snapshots/tick_120.evol").exists());
+    let final_bytes = fs::read(out.join("final.evol")).unwrap();
+    let last = Simulation::load(&final_bytes).unwrap();
+    assert_eq!(last.tick(), 120);
+    // The final world is the one the run was at, not reclustered for `species.json`.
+    assert_eq!(
+        final_bytes,
+        fs::read(out.join("snapshots/tick_120.evol")).unwrap()
+    );
+
+    assert!(fs::read_to_string(out.join("lineage.nwk"))
+        .unwrap()
+        .ends_with(");"));
+    for file in ["lineage.json", "species.json", "config.json"].iter() {
+        let json = fs::read_to_string(out.join(file)).unwrap();
+        serde_json::from_str::<serde_json::Value>(&json).unwrap();
+    }
+
+    let _ = fs::remove_dir_all(&out);
+    let _ = fs::remove_file(&config);
+}
+
+#[test]
+fn same_seed_same_run() {
+    let run = |name: &str| {
+        let out = out_dir(name);
+        let output = evol(&[
+            "--ticks",
+            "60",
+            "--entities",
+            "30",
+            "--plants",
+            "200",
+            "--out",
+            out.to_str().unwrap(),
+        ]);
+        assert!(output.status.success(), "{:?}", output);
+        let snapshot = fs::read(out.join("final.evol")).unwrap();
+        let _ = fs::remove_dir_all(&out);
+        snapshot
+    };
+
+    assert_eq!(run("a"), run("b"));
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    for args in [
+        &["--seed", "1"][..],
+        &["--ticks", "ten"][..],
+        &["--ticks", "10", "--frobnicate", "1"][..],
+        // Nothing alive to simulate.
+        &["--ticks", "10", "--out", "/nonexistent/never"][..],
+    ]
+    .iter()
+    {
+        let output = evol(args);
+        assert!(!output.status.success(), "{:?}", args);
+        assert!(!output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn sweeps_write_a_summary() {
+    let out = out_dir("sweep");
+    let experiment = out.with_extension("json");
+    fs::write(
+        &experiment,
+        r#"{ "ticks": 60, "seeds": [1, 2],
+             "base": { "initial_entities": 30, "initial_plants": 200 },
+             "parameters": [{ "name": "predator_ratio", "values": [1.1, 1.3] }] }"#,
+    )
+    .unwrap();
+
+    let output = evol(&[
+        "sweep",
+        experiment.to_str().unwrap(),
+        "--out",
+        out.to_str().unwrap(),
+        "--threads",
+        "2",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let summary = fs::read_to_string(out.join("summary.csv")).unwrap();
+    assert_eq!(summary.lines().count(), 1 + 2);
+    let runs = fs::read_to_string(out.join("runs.csv")).unwrap();
+    assert_eq!(runs.lines().count(), 1 + 4);
+
+    let _ = fs::remove_dir_all(&out);
+    let _ = fs::remove_file(&experiment);
+}
@@ -0,0 +1,101 @@
+//! Seasons and days.
+
+mod common;
+
+use common::DT;
+use game::{Clock, ConfigError, Season, SimConfig, Simulation};
+
+fn config(year_length: u64, day_length: u64) -> SimConfig {
+    SimConfig {
+        year_length,
+        day_length,
+        ..SimConfig::default()
+    }
+}
+
+#[test]
+fn without_cycles_nothing_changes() {
+    let config = SimConfig::default();
+
+    for tick in [0, 1, 12345].iter() {
+        let clock = Clock::new(*tick, &config);
+
+        assert_eq!(clock.season, None);
+        assert_eq!(clock.daylight, 1.0);
+        assert_eq!(clock.growth_factor(&config), 1.0);
+        assert_eq!(clock.regeneration_factor(&config), 1.0);
+        assert_eq!(clock.sense_factor(&config), 1.0);
+    }
+}
+
+#[test]
+fn seasons_follow_each_other() {
+    let config = config(400, 0);
+
+    let seasons: Vec<Option<Season>> = (0..5)
+        .map(|quarter| Clock::new(quarter * 100 + 50, &config).season)
+        .collect();
+
+    assert_eq!(
+        seasons,
+        [
+            Some(Season::Spring),
+            Some(Season::Summer),
+            Some(Season::Autumn),
+            Some(Season::Winter),
+            Some(Season::Spring),
+        ]
+    );
+    assert_eq!(Clock::new(300, &config).year_phase, 0.75);
+    assert_eq!(
+        Clock::new(350, &config).growth_factor(&config),
+        config.season_growth[3]
+    );
+}
+
+#[test]
+fn nights_shorten_sight() {
+    let config = config(0, 100);
+
+    let noon = Clock::new(25, &config);
+    let midnight = Clock::new(75, &config);
+
+    assert!((noon.daylight - 1.0).abs() < 1e-6);
+    assert!(midnight.daylight.abs() < 1e-6);
+    assert!((noon.sense_factor(&config) - 1.0).abs() < 1e-6);
+    assert!((midnight.sense_factor(&config) - config.night_sense).abs() < 1e-6);
+}
+
+#[test]
+fn plants_do_not_grow_in_a_barren_season() {
+    let config = SimConfig {
+        year_length: 1000,
+        season_growth: [0.0; 4],
+        initial_plants: 500,
+        ..SimConfig::default()
+    };
+    let mut simulation = Simulation::with_config(1, config);
+
+    for _ in 0..100 {
+        simulation.step(DT);
+    }
+
+    assert_eq!(simulation.plants().len(), 500);
+}
+
+#[test]
+fn rejects_invalid_cycles() {
+    let invalid = [
+        r#"{ "season_growth": [1, 1, -1, 1] }"#,
+        r#"{ "season_regeneration": [1, 1, 1, -0.5] }"#,
+        r#"{ "night_sense": 1.5 }"#,
+    ];
+
+    for json in invalid.iter() {
+        assert!(
+            matches!(SimConfig::from_json(json), Err(ConfigError::Invalid(_))),
+            "{}",
+            json
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Helpers shared by the integration tests. Every test crate uses only some of them.
+
+#![allow(dead_code)]
+
+use game::{Entity, Genome, SimConfig, Simulation};
+use glam::Vec2;
+
+/// One step at 60 steps per second.
+pub const DT: f32 = 1000.0 / 60.0;
+
+/// A world of `seed` populated with `entities` and `plants`, then stepped `ticks` times.
+pub fn running_world(seed: u64, entities: usize, plants: usize, ticks: usize) -> Simulation {
+    let mut simulation = Simulation::new(seed);
+    simulation.populate(entities, plants);
+
+    for _ in 0..ticks {
+        simulation.step(DT);
+    }
+
+    simulation
+}
+
+/// A world tuned by `config` where no plants seed, for tests to fill by hand.
+pub fn empty_world(config: SimConfig) -> Simulation {
+    Simulation::with_config(
+        1,
+        SimConfig {
+            plant_growth_rate: 0.0,
+            ..config
+        },
+    )
+}
+
+/// An entity with the genome `genes` at `position`, drawing its wandering direction from the
+/// RNG of `simulation`.
+pub fn entity(simulation: &mut Simulation, genes: &[f32], position: Vec2, energy: f32) -> Entity {
+    Entity::new(
+        Genome::new(genes.to_vec()),
+        position,
+        energy,
+        simulation.rng(),
+    )
+}
+
+/// Adds `entity(simulation, genes, position, energy)` to `simulation`.
+pub fn add_entity(simulation: &mut Simulation, genes: &[f32], position: Vec2, energy: f32) {
+    let entity = entity(simulation, genes, position, energy);
+    simulation.add_entity(entity);
+}
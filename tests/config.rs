@@ -0,0 +1,61 @@
+//! Loading and validating `SimConfig`.
+
+use game::{ConfigError, SimConfig, Simulation};
+
+#[test]
+fn missing_fields_keep_their_defaults() {
+    let config = SimConfig::from_json(r#"{ "prey_ratio": 0.5, "initial_entities": 3 }"#).unwrap();
+
+    assert_eq!(config.prey_ratio, 0.5);
+    assert_eq!(config.initial_entities, 3);
+    assert_eq!(config.predator_ratio, SimConfig::default().predator_ratio);
+}
+
+#[test]
+fn round_trips_through_json() {
+    let config = SimConfig {
+        mutation_amount: 0.25,
+        ..SimConfig::default()
+    };
+
+    assert_eq!(SimConfig::from_json(&config.to_json()).unwrap(), config);
+}
+
+#[test]
+fn rejects_unknown_fields_and_invalid_values() {
+    assert!(matches!(
+        SimConfig::from_json(r#"{ "prey_ration": 0.5 }"#),
+        Err(ConfigError::Parse(_))
+    ));
+    assert!(matches!(
+        SimConfig::from_json(r#"{ "prey_ratio": 1.5 }"#),
+        Err(ConfigError::Invalid(_))
+    ));
+    assert!(matches!(
+        SimConfig::from_json(r#"{ "reproduction_threshold": 10 }"#),
+        Err(ConfigError::Invalid(_))
+    ));
+    assert!(matches!(
+        SimConfig::from_json(r#"{ "min_trait": 0.8, "max_trait": 0.5 }"#),
+        Err(ConfigError::Invalid(_))
+    ));
+}
+
+#[test]
+fn creates_the_initial_population() {
+    let config = SimConfig {
+        initial_entities: 12,
+        initial_plants: 34,
+        min_trait: 0.4,
+        max_trait: 0.6,
+        ..SimConfig::default()
+    };
+    let simulation = Simulation::with_config(0, config);
+
+    assert_eq!(simulation.entities().len(), 12);
+    assert_eq!(simulation.plants().len(), 34);
+    assert!(simulation
+        .entities()
+        .iter()
+        .all(|entity| entity.size() >= 0.4 && entity.size() <= 0.6));
+}
@@ -0,0 +1,147 @@
+//! Corpses left by starved entities, rotting and scavenged.
+
+mod common;
+
+use common::{add_entity, empty_world, DT};
+use game::{block_to_world, ConfigError, Plant, SimConfig, Simulation};
+use glam::Vec2;
+
+// A world without plants, where the land only changes under corpses.
+fn corpse_world(corpse_energy: f32) -> Simulation {
+    empty_world(SimConfig {
+        fertility_regeneration: 0.0,
+        fertility_diffusion: 0.0,
+        corpse_energy,
+        ..SimConfig::default()
+    })
+}
+
+#[test]
+fn starved_entities_only_leave_corpses_when_they_have_energy() {
+    let position = block_to_world(5, 5);
+
+    let mut simulation = corpse_world(0.0);
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], position, 0.1);
+    simulation.step(DT);
+    assert!(simulation.entities().is_empty());
+    assert!(simulation.corpses().is_empty());
+
+    let mut simulation = corpse_world(1000.0);
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], position, 0.1);
+    simulation.step(DT);
+    assert!(simulation.entities().is_empty());
+    assert_eq!(simulation.corpses().len(), 1);
+    assert!(simulation.corpses()[0].position().distance(position) < 1.0);
+}
+
+#[test]
+fn corpses_rot_into_the_land() {
+    let config = SimConfig {
+        death_fertility: 0.1,
+        corpse_decay: 0.1,
+        ..corpse_world(1000.0).config().clone()
+    };
+    let mut simulation = Simulation::with_config(1, config);
+    let position = block_to_world(5, 5);
+    let before = simulation.terrain().block_at(position).unwrap().fertility;
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], position, 0.1);
+
+    simulation.step(DT);
+    let corpse = &simulation.corpses()[0];
+    let released = 0.1 - corpse.fertility();
+    assert!(released > 0.0 && released < 0.1);
+    let fertility = simulation.terrain().block_at(position).unwrap().fertility;
+    assert!((fertility - before - released).abs() < 1e-6);
+
+    for _ in 0..1000 {
+        simulation.step(DT);
+    }
+
+    assert!(simulation.corpses().is_empty());
+    let fertility = simulation.terrain().block_at(position).unwrap().fertility;
+    assert!((fertility - before - 0.1).abs() < 1e-5, "{}", fertility);
+}
+
+// How much energy an entity has after a few steps next to an entity that starves.
+fn energy_next_to_a_starving_entity(corpse_energy: f32) -> (f32, usize) {
+    let mut simulation = corpse_world(corpse_energy);
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], Vec2::ZERO, 5000.0);
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], Vec2::new(0.1, 0.0), 0.1);
+
+    for _ in 0..3 {
+        simulation.step(DT);
+    }
+
+    assert_eq!(simulation.entities().len(), 1);
+    (simulation.entities()[0].energy, simulation.corpses().len())
+}
+
+#[test]
+fn scavengers_eat_corpses() {
+    let (hungry, _) = energy_next_to_a_starving_entity(0.0);
+    let (fed, corpses) = energy_next_to_a_starving_entity(1000.0);
+
+    assert!(fed > hungry, "{} {}", fed, hungry);
+    assert_eq!(corpses, 0);
+}
+
+#[test]
+fn eaten_entities_leave_no_corpse() {
+    let mut simulation = corpse_world(1000.0);
+    add_entity(&mut simulation, &[0.9, 0.5, 0.5], Vec2::ZERO, 5000.0);
+    add_entity(
+        &mut simulation,
+        &[0.1, 0.5, 0.5],
+        Vec2::new(0.1, 0.0),
+        5000.0,
+    );
+
+    simulation.step(DT);
+
+    assert_eq!(simulation.entities().len(), 1);
+    assert!(simulation.corpses().is_empty());
+}
+
+#[test]
+fn entities_that_eat_after_being_eaten_are_not_marked_eaten() {
+    let mut simulation = corpse_world(1000.0);
+    add_entity(&mut simulation, &[1.0, 0.5, 0.5], Vec2::ZERO, 5000.0);
+    add_entity(
+        &mut simulation,
+        &[0.3, 0.5, 0.5],
+        Vec2::new(1.2, 0.0),
+        5000.0,
+    );
+    simulation.add_plant(Plant::new(Vec2::new(1.7, 0.0), 0.3));
+
+    simulation.step(DT);
+
+    // The prey was eaten, then ate the plant and survived.
+    assert!(simulation.plants().is_empty());
+    let prey = simulation
+        .entities()
+        .iter()
+        .find(|entity| entity.size() == 0.3)
+        .unwrap();
+    assert!(prey.energy > 0.0);
+    assert!(!prey.eaten());
+}
+
+#[test]
+fn rejects_invalid_corpse_settings() {
+    let invalid = [
+        r#"{ "corpse_energy": -1 }"#,
+        r#"{ "corpse_min_energy": 0 }"#,
+        r#"{ "corpse_energy": 100, "corpse_decay": 0 }"#,
+        r#"{ "corpse_decay": 2 }"#,
+        r#"{ "corpse_decay": -0.5 }"#,
+    ];
+
+    for json in invalid.iter() {
+        assert!(
+            matches!(SimConfig::from_json(json), Err(ConfigError::Invalid(_))),
+            "{}",
+            json
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! The diet gene: how well entities digest plants and meat.
+
+mod common;
+
+use common::{add_entity, entity, DT};
+use game::{
+    draw_world, rgba, Behavior, ColorMode, ConfigError, Plant, RenderBuffer, SimConfig, Simulation,
+    DIET, GRID_SIZE,
+};
+use glam::Vec2;
+
+fn evolving() -> SimConfig {
+    SimConfig {
+        evolving_diet: true,
+        plant_growth_rate: 0.0,
+        reproduction_threshold: 1e9,
+        ..SimConfig::default()
+    }
+}
+
+// How much energy an entity with `diet` and `behavior` has after a step on top of a plant, and
+// whether it ate it.
+fn energy_after_eating_a_plant(diet: f32, behavior: Behavior) -> (f32, bool) {
+    let config = SimConfig {
+        behavior,
+        ..evolving()
+    };
+    let mut simulation = Simulation::with_config(0, config);
+    simulation.add_plant(Plant::new(Vec2::new(0.1, 0.0), 0.3));
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5, diet], Vec2::ZERO, 5000.0);
+
+    simulation.step(DT);
+
+    (
+        simulation.entities()[0].energy,
+        simulation.plants().is_empty(),
+    )
+}
+
+// How much energy an entity with `diet` and `behavior` has after a step on top of smaller prey,
+// and whether it ate it.
+fn energy_after_eating_prey(diet: f32, behavior: Behavior) -> (f32, bool) {
+    let config = SimConfig {
+        behavior,
+        ..evolving()
+    };
+    let mut simulation = Simulation::with_config(0, config);
+    add_entity(&mut simulation, &[0.9, 0.5, 0.5, diet], Vec2::ZERO, 5000.0);
+    add_entity(
+        &mut simulation,
+        &[0.1, 0.5, 0.5, 0.0],
+        Vec2::new(0.1, 0.0),
+        5000.0,
+    );
+
+    simulation.step(DT);
+
+    let ate = simulation.entities().len() == 1;
+    (simulation.entities()[0].energy, ate)
+}
+
+#[test]
+fn diet_is_only_a_gene_when_it_evolves() {
+    let simulation = Simulation::new(0);
+    assert_eq!(simulation.genome_layout().index_of("diet"), None);
+
+    let simulation = Simulation::with_config(
+        0,
+        SimConfig {
+            behavior: Behavior::Neural,
+            ..evolving()
+        },
+    );
+    let layout = simulation.genome_layout();
+    assert_eq!(layout.index_of("diet"), Some(DIET));
+    assert_eq!(layout.brain().unwrap().start, DIET + 1);
+}
+
+#[test]
+fn entities_digest_everything_fully_without_diet() {
+    let mut simulation = Simulation::new(0);
+    let entity = entity(&mut simulation, &[0.5, 0.5, 0.5], Vec2::ZERO, 100.0);
+
+    assert_eq!(entity.diet(simulation.config()), 0.5);
+    assert_eq!(entity.plant_efficiency(simulation.config()), 1.0);
+    assert_eq!(entity.meat_efficiency(simulation.config()), 1.0);
+}
+
+#[test]
+fn specialists_gain_more_from_their_food() {
+    let (herbivore, _) = energy_after_eating_a_plant(0.0, Behavior::Rules);
+    let (omnivore, _) = energy_after_eating_a_plant(0.5, Behavior::Rules);
+    assert!(herbivore > omnivore);
+
+    let (carnivore, ate) = energy_after_eating_prey(1.0, Behavior::Rules);
+    assert!(ate);
+    let (omnivore, ate) = energy_after_eating_prey(0.5, Behavior::Rules);
+    assert!(ate);
+    assert!(carnivore > omnivore);
+}
+
+#[test]
+fn herbivores_following_the_rules_leave_prey_alone() {
+    let (_, ate) = energy_after_eating_prey(0.0, Behavior::Rules);
+
+    assert!(!ate);
+}
+
+#[test]
+fn herbivores_with_brains_leave_prey_alone() {
+    let (_, ate) = energy_after_eating_prey(0.0, Behavior::Neural);
+    assert!(!ate);
+
+    let (_, ate) = energy_after_eating_prey(1.0, Behavior::Neural);
+    assert!(ate);
+}
+
+#[test]
+fn carnivores_leave_plants_alone() {
+    for behavior in [Behavior::Rules, Behavior::Neural].iter() {
+        let (_, ate) = energy_after_eating_a_plant(1.0, *behavior);
+        assert!(!ate, "{:?}", behavior);
+
+        let (_, ate) = energy_after_eating_a_plant(0.0, *behavior);
+        assert!(ate, "{:?}", behavior);
+    }
+}
+
+#[test]
+fn colors_entities_by_diet() {
+    let mut simulation = Simulation::with_config(0, evolving());
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5, 1.0], Vec2::ZERO, 5000.0);
+
+    let mut buffer = RenderBuffer::new();
+    draw_world(&simulation, ColorMode::Diet, &mut buffer);
+
+    assert_eq!(ColorMode::from_name("diet"), Some(ColorMode::Diet));
+    assert_eq!(buffer.colors()[GRID_SIZE * GRID_SIZE], rgba(255, 0, 0, 255));
+}
+
+#[test]
+fn rejects_invalid_diet_settings() {
+    let invalid = [
+        r#"{ "diet_specialization": -1 }"#,
+        r#"{ "evolving_diet": true, "genes": [{ "name": "diet", "min": 0, "max": 2 }] }"#,
+    ];
+
+    for json in invalid.iter() {
+        assert!(
+            matches!(SimConfig::from_json(json), Err(ConfigError::Invalid(_))),
+            "{}",
+            json
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Parameter sweeps over many worlds.
+
+use game::{Experiment, ExperimentError, Sampling};
+
+fn experiment(json: &str) -> Experiment {
+    Experiment::from_json(json).unwrap()
+}
+
+#[test]
+fn grid_runs_every_combination() {
+    let experiment = experiment(
+        r#"{ "ticks": 10,
+             "parameters": [
+                 { "name": "predator_ratio", "values": [1.1, 1.5] },
+                 { "name": "plant_carrying_capacity", "values": [100, 200, 300] }
+             ] }"#,
+    );
+
+    let points = experiment.points().unwrap();
+
+    let values: Vec<Vec<f64>> = points.iter().map(|p| p.values.clone()).collect();
+    assert_eq!(
+        values,
+        [
+            [1.1, 100.0],
+            [1.1, 200.0],
+            [1.1, 300.0],
+            [1.5, 100.0],
+            [1.5, 200.0],
+            [1.5, 300.0],
+        ]
+    );
+    assert_eq!(points[4].config.predator_ratio, 1.5);
+    assert_eq!(points[4].config.plant_carrying_capacity, 200);
+}
+
+#[test]
+fn random_sampling_stays_between_the_extremes() {
+    let experiment = experiment(
+        r#"{ "ticks": 10,
+             "sampling": { "method": "random", "samples": 50, "seed": 9 },
+             "parameters": [
+                 { "name": "prey_ratio", "values": [0.5, 0.9] },
+                 { "name": "plant_carrying_capacity", "values": [10, 20] }
+             ] }"#,
+    );
+    assert_eq!(
+        experiment.sampling,
+        Sampling::Random {
+            samples: 50,
+            seed: 9
+        }
+    );
+
+    let points = experiment.points().unwrap();
+
+    assert_eq!(points.len(), 50);
+    assert_eq!(points, experiment.points().unwrap());
+    for point in points.iter() {
+        assert!((0.5..=0.9).contains(&point.values[0]));
+        assert_eq!(point.values[1].fract(), 0.0);
+        assert_eq!(point.config.plant_carrying_capacity as f64, point.values[1]);
+    }
+}
+
+#[test]
+fn rejects_invalid_experiments() {
+    let invalid = [
+        r#"{ "ticks": 10, "parameters": [{ "name": "nonexistent", "values": [1] }] }"#,
+        r#"{ "ticks": 10, "parameters": [{ "name": "behavior", "values": [1] }] }"#,
+        r#"{ "ticks": 10, "parameters": [{ "name": "prey_ratio", "values": [] }] }"#,
+        r#"{ "ticks": 10, "parameters": [{ "name": "prey_ratio", "values": [0.5, 2] }] }"#,
+        r#"{ "ticks": 10, "seeds": [] }"#,
+    ];
+
+    for json in invalid.iter() {
+        assert!(
+            matches!(
+                Experiment::from_json(json),
+                Err(ExperimentError::Invalid(_))
+            ),
+            "{}",
+            json
+        );
+    }
+    assert!(matches!(
+        Experiment::from_json("{}"),
+        Err(ExperimentError::Parse(_))
+    ));
+}
+
+#[test]
+fn results_do_not_depend_on_the_threads() {
+    let experiment = experiment(
+        r#"{ "ticks": 120, "seeds": [1, 2, 3],
+             "base": { "initial_entities": 30, "initial_plants": 200 },
+             "parameters": [{ "name": "reproduction_threshold", "values": [5000, 10000] }] }"#,
+    );
+
+    let results = experiment.run(1).unwrap();
+
+    assert_eq!(results, experiment.run(4).unwrap());
+    assert_eq!(results.parameters, ["reproduction_threshold"]);
+    assert_eq!(results.genes, ["size", "sense_distance", "velocity_mag"]);
+    assert_eq!(results.points.len(), 2);
+    for point in results.points.iter() {
+        let seeds: Vec<u64> = point.runs.iter().map(|run| run.seed).collect();
+        assert_eq!(seeds, [1, 2, 3]);
+        for run in point.runs.iter() {
+            assert!(run.survival_ticks <= 120);
+            assert_eq!(run.extinct, run.final_entities == 0);
+            assert_eq!(run.gene_means.len(), 3);
+        }
+    }
+
+    let csv = results.to_csv();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "reproduction_threshold,runs,extinctions,survival_ticks,final_entities,final_plants,\
+         size_mean,sense_distance_mean,velocity_mag_mean"
+    );
+    assert!(lines.next().unwrap().starts_with("5000,3,"));
+    assert!(lines.next().unwrap().starts_with("10000,3,"));
+    assert_eq!(results.runs_to_csv().lines().count(), 1 + 6);
+}
+
+#[test]
+fn extinct_runs_are_left_out_of_the_gene_means() {
+    let experiment = experiment(r#"{ "ticks": 100, "seeds": [1, 2] }"#);
+
+    let results = experiment.run(0).unwrap();
+
+    for run in results.points[0].runs.iter() {
+        assert!(run.extinct);
+        assert_eq!(run.survival_ticks, 0);
+    }
+    let csv = results.to_csv();
+    assert_eq!(csv.lines().nth(1).unwrap(), "2,2,0,0,0,,,");
+}
@@ -0,0 +1,163 @@
+//! Genes, their specs and how they are passed on.
+
+mod common;
+
+use common::DT;
+use game::{
+    ConfigError, GeneKind, GeneSpec, Genome, GenomeLayout, Mutation, SimConfig, Simulation,
+};
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+fn gene(name: &str, kind: GeneKind, min: f32, max: f32, mutation: Mutation) -> GeneSpec {
+    GeneSpec {
+        name: name.to_string(),
+        kind,
+        min,
+        max,
+        mutation,
+        rate: 1.0,
+    }
+}
+
+fn layout(genes: Vec<GeneSpec>) -> GenomeLayout {
+    GenomeLayout::new(&SimConfig {
+        genes,
+        ..SimConfig::default()
+    })
+}
+
+#[test]
+fn built_in_genes_come_first_and_can_be_replaced() {
+    let layout = layout(vec![
+        gene("aggression", GeneKind::Real, 0.0, 1.0, Mutation::default()),
+        gene("size", GeneKind::Real, 0.2, 0.3, Mutation::default()),
+    ]);
+
+    let names: Vec<&str> = layout.genes().iter().map(|g| g.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["size", "sense_distance", "velocity_mag", "aggression"]
+    );
+    assert_eq!(layout.genes()[0].max, 0.3);
+    assert_eq!(layout.index_of("aggression"), Some(3));
+}
+
+#[test]
+fn mutations_respect_the_kind_and_the_range() {
+    let layout = layout(vec![
+        gene(
+            "lifespan",
+            GeneKind::Integer,
+            1.0,
+            5.0,
+            Mutation::Gaussian { sigma: 3.0 },
+        ),
+        gene("colorful", GeneKind::Flag, 0.0, 1.0, Mutation::default()),
+        gene(
+            "aggression",
+            GeneKind::Real,
+            -1.0,
+            1.0,
+            Mutation::Uniform { amount: 0.5 },
+        ),
+    ]);
+    let mut rng = Pcg32::seed_from_u64(3);
+    let mut genome = Genome::random(&layout, &mut rng);
+
+    for _ in 0..1000 {
+        let child = genome.mutated(&layout, &mut rng);
+
+        for (value, spec) in child.values().iter().zip(layout.genes()) {
+            assert!(
+                *value >= spec.min && *value <= spec.max,
+                "{} = {}",
+                spec.name,
+                value
+            );
+        }
+        assert_eq!(child.get(3).fract(), 0.0);
+        assert_eq!(child.get(4), 1.0 - genome.get(4));
+
+        genome = child;
+    }
+}
+
+#[test]
+fn genes_with_a_zero_rate_never_mutate() {
+    let mut frozen = gene("frozen", GeneKind::Real, 0.0, 1.0, Mutation::default());
+    frozen.rate = 0.0;
+    let layout = layout(vec![frozen]);
+    let mut rng = Pcg32::seed_from_u64(3);
+    let genome = Genome::random(&layout, &mut rng);
+
+    for _ in 0..100 {
+        assert_eq!(genome.mutated(&layout, &mut rng).get(3), genome.get(3));
+    }
+}
+
+#[test]
+fn genes_come_from_the_config() {
+    let config = SimConfig::from_json(
+        r#"{ "genes": [
+            { "name": "aggression", "min": 0, "max": 1 },
+            { "name": "lifespan", "kind": "integer", "min": 10, "max": 100,
+              "mutation": { "distribution": "gaussian", "sigma": 5 }, "rate": 0.2 }
+        ] }"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.genes[0].mutation, Mutation::Relative { amount: 0.1 });
+    assert_eq!(config.genes[0].rate, 1.0);
+    assert_eq!(config.genes[1].kind, GeneKind::Integer);
+    assert_eq!(config.genes[1].mutation, Mutation::Gaussian { sigma: 5.0 });
+}
+
+#[test]
+fn rejects_invalid_genes() {
+    let invalid = [
+        r#"{ "genes": [{ "name": "a", "min": 1, "max": 0 }] }"#,
+        r#"{ "genes": [{ "name": "a", "min": 0, "max": 1, "rate": 2 }] }"#,
+        r#"{ "genes": [{ "name": "a", "kind": "integer", "min": 0.2, "max": 0.8 }] }"#,
+        r#"{ "genes": [{ "name": "size", "min": 0, "max": 2 }] }"#,
+        r#"{ "genes": [{ "name": "a", "min": 0, "max": 1 }, { "name": "a", "min": 0, "max": 1 }] }"#,
+    ];
+
+    for json in invalid.iter() {
+        assert!(
+            matches!(SimConfig::from_json(json), Err(ConfigError::Invalid(_))),
+            "{}",
+            json
+        );
+    }
+}
+
+#[test]
+fn extra_genes_are_inherited() {
+    let config = SimConfig {
+        genes: vec![gene(
+            "aggression",
+            GeneKind::Real,
+            0.0,
+            1.0,
+            Mutation::default(),
+        )],
+        ..SimConfig::default()
+    };
+    let mut simulation = Simulation::with_config(4, config);
+    simulation.populate(100, 1000);
+
+    for _ in 0..300 {
+        simulation.step(DT);
+    }
+
+    let layout = simulation.genome_layout();
+    assert!(simulation
+        .entities()
+        .iter()
+        .any(|e| e.parent_id().is_some()));
+    for entity in simulation.entities() {
+        assert_eq!(entity.genome().values().len(), 4);
+        assert!(entity.genome().gene(layout, "aggression").is_some());
+    }
+}
@@ -0,0 +1,123 @@
+//! Ids must stay attached to their holder and never be handed out twice.
+
+mod common;
+
+use std::collections::HashSet;
+
+use common::{running_world, DT};
+use game::{Id, IdMap, Simulation};
+
+fn assert_indexed(simulation: &Simulation) {
+    for (index, entity) in simulation.entities().iter().enumerate() {
+        assert_ne!(entity.id(), Id::UNASSIGNED);
+        assert_eq!(simulation.entity_index(entity.id()), Some(index));
+    }
+
+    for (index, plant) in simulation.plants().iter().enumerate() {
+        assert_ne!(plant.id(), Id::UNASSIGNED);
+        assert_eq!(simulation.plant_index(plant.id()), Some(index));
+    }
+}
+
+#[test]
+fn released_slots_come_back_with_a_new_generation() {
+    let mut ids = IdMap::new();
+    let first = ids.allocate();
+    ids.set_index(first, 0);
+    ids.release(first);
+
+    let second = ids.allocate();
+
+    assert_eq!(first.slot(), second.slot());
+    assert_ne!(first, second);
+    assert_eq!(ids.index_of(first), None);
+}
+
+#[test]
+fn bits_round_trip() {
+    let mut ids = IdMap::new();
+    for _ in 0..3 {
+        let id = ids.allocate();
+        ids.release(id);
+    }
+    let id = ids.allocate();
+
+    assert_eq!(id.generation(), 3);
+    assert_eq!(Id::from_bits(id.to_bits()), id);
+}
+
+#[test]
+fn ids_follow_their_holders() {
+    let mut simulation = Simulation::new(5);
+    simulation.populate(100, 1000);
+    assert_indexed(&simulation);
+
+    for _ in 0..200 {
+        simulation.step(DT);
+        assert_indexed(&simulation);
+    }
+}
+
+#[test]
+fn ids_are_never_reused() {
+    let mut simulation = Simulation::new(5);
+    simulation.populate(100, 1000);
+
+    let mut seen = HashSet::new();
+    let mut alive: HashSet<_> = HashSet::new();
+
+    for _ in 0..300 {
+        simulation.step(DT);
+
+        let now: HashSet<_> = simulation.entities().iter().map(|e| e.id()).collect();
+        for id in now.difference(&alive) {
+            assert!(seen.insert(*id), "{:?} was handed out twice", id);
+        }
+        alive = now;
+    }
+}
+
+#[test]
+fn children_know_their_parent() {
+    let mut simulation = Simulation::new(5);
+    simulation.populate(100, 1000);
+    let founders: HashSet<_> = simulation.entities().iter().map(|e| e.id()).collect();
+    let mut known = founders.clone();
+    let mut children = 0;
+
+    for _ in 0..300 {
+        simulation.step(DT);
+
+        for entity in simulation.entities() {
+            if known.insert(entity.id()) {
+                let parent = entity.parent_id().expect("a newborn has a parent");
+                assert!(known.contains(&parent));
+                children += 1;
+            }
+        }
+    }
+
+    assert!(children > 0);
+    for id in founders {
+        if let Some(founder) = simulation.entity(id) {
+            assert_eq!(founder.parent_id(), None);
+        }
+    }
+}
+
+#[test]
+fn snapshots_keep_ids() {
+    let mut original = running_world(5, 100, 1000, 100);
+    let mut loaded = Simulation::load(&original.save()).unwrap();
+    assert_indexed(&loaded);
+
+    for _ in 0..50 {
+        original.step(DT);
+        loaded.step(DT);
+    }
+
+    let ids = |simulation: &Simulation| -> Vec<Id> {
+        simulation.entities().iter().map(|e| e.id()).collect()
+    };
+    assert_eq!(ids(&original), ids(&loaded));
+}
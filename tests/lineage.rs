@@ -0,0 +1,120 @@
+//! The lineage must match what happened in the world and survive pruning and export.
+
+mod common;
+
+use common::{add_entity, running_world, DT};
+use game::{Id, Simulation};
+use glam::Vec2;
+
+#[test]
+fn records_births_and_deaths() {
+    let simulation = running_world(8, 100, 1000, 300);
+    let lineage = simulation.lineage();
+
+    for entity in simulation.entities() {
+        let record = lineage
+            .get(entity.id())
+            .expect("every living entity is recorded");
+        assert!(record.is_alive());
+        assert_eq!(record.parent, entity.parent_id());
+        assert_eq!(&record.genome, entity.genome());
+    }
+
+    let living = simulation.entities().len();
+    let dead = lineage
+        .records()
+        .filter(|record| !record.is_alive())
+        .count();
+    assert_eq!(lineage.len(), living + dead);
+    assert!(dead > 0);
+
+    for record in lineage.records() {
+        if let Some(death) = record.death_tick {
+            assert!(record.birth_tick <= death && death <= simulation.tick());
+        }
+        if let Some(parent) = record.parent.and_then(|parent| lineage.get(parent)) {
+            assert!(parent.birth_tick < record.birth_tick);
+        }
+    }
+}
+
+#[test]
+fn records_when_an_entity_starves() {
+    let mut simulation = Simulation::new(0);
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], Vec2::ZERO, 1.0);
+    let id = simulation.entities()[0].id();
+
+    simulation.step(DT);
+
+    let record = simulation.lineage().get(id).unwrap();
+    assert_eq!(record.birth_tick, 0);
+    assert_eq!(record.death_tick, Some(1));
+}
+
+#[test]
+fn pruning_keeps_the_ancestry_of_the_living() {
+    let mut simulation = running_world(8, 100, 1000, 300);
+    let before = simulation.lineage().len();
+
+    let removed = simulation.lineage_mut().prune_extinct();
+    let lineage = simulation.lineage();
+
+    assert_eq!(lineage.len(), before - removed);
+    for entity in simulation.entities() {
+        let mut current = Some(entity.id());
+        while let Some(id) = current {
+            current = lineage.get(id).expect("ancestors are kept").parent;
+        }
+    }
+
+    // Every dead record left is the ancestor of a living entity, so a second pass is a no-op.
+    assert_eq!(simulation.lineage_mut().prune_extinct(), 0);
+}
+
+#[test]
+fn newick_mentions_every_record_once() {
+    let simulation = running_world(8, 100, 1000, 300);
+    let newick = simulation.lineage().to_newick();
+
+    assert!(newick.starts_with('(') && newick.ends_with(");"));
+    assert_eq!(newick.matches('(').count(), newick.matches(')').count());
+
+    let labels: Vec<u64> = newick
+        .split(['(', ')', ','])
+        .filter(|node| !node.is_empty() && *node != ";")
+        .map(|node| node.split(':').next().unwrap().parse().unwrap())
+        .collect();
+    assert_eq!(labels.len(), simulation.lineage().len());
+
+    for bits in labels {
+        assert!(simulation.lineage().contains(Id::from_bits(bits)));
+    }
+}
+
+#[test]
+fn json_lists_every_record() {
+    let simulation = running_world(8, 100, 1000, 100);
+    let records: Vec<serde_json::Value> =
+        serde_json::from_str(&simulation.lineage().to_json(simulation.genome_layout())).unwrap();
+
+    assert_eq!(records.len(), simulation.lineage().len());
+    for record in records {
+        let id = Id::from_bits(record["id"].as_u64().unwrap());
+        let expected = simulation.lineage().get(id).unwrap();
+        assert_eq!(record["birth_tick"].as_u64(), Some(expected.birth_tick));
+        assert_eq!(record["death_tick"].as_u64(), expected.death_tick);
+    }
+}
+
+#[test]
+fn snapshots_keep_the_lineage() {
+    let original = running_world(8, 100, 1000, 100);
+
+    let loaded = Simulation::load_json(&original.save_json()).unwrap();
+
+    let layout = original.genome_layout();
+    assert_eq!(
+        loaded.lineage().to_json(layout),
+        original.lineage().to_json(layout)
+    );
+}
@@ -0,0 +1,217 @@
+//! Plants growing, aging and dying.
+
+mod common;
+
+use common::{add_entity, empty_world, DT};
+use game::{
+    block_to_world, ConfigError, GeneKind, Genome, Plant, SimConfig, Simulation, GRID_SIZE,
+    SEED_COUNT, TOXICITY,
+};
+use glam::Vec2;
+
+fn center() -> Vec2 {
+    block_to_world(GRID_SIZE / 2, GRID_SIZE / 2)
+}
+
+#[test]
+fn plants_keep_their_size_by_default() {
+    let mut simulation = empty_world(SimConfig::default());
+    simulation.add_plant(Plant::new(center(), 0.15));
+
+    for _ in 0..100 {
+        simulation.step(DT);
+    }
+
+    assert_eq!(simulation.plants()[0].size(), 0.15);
+    assert_eq!(simulation.plants()[0].age(), 100);
+}
+
+#[test]
+fn plants_grow_faster_on_fertile_land() {
+    let config = SimConfig {
+        plant_growth_speed: 0.01,
+        plant_max_size: 0.5,
+        ..SimConfig::default()
+    };
+    let mut simulation = empty_world(config);
+    let corner = block_to_world(0, 0);
+    simulation.add_plant(Plant::new(center(), 0.1));
+    simulation.add_plant(Plant::new(corner, 0.1));
+
+    let mut previous = 0.1;
+    for _ in 0..1000 {
+        simulation.step(DT);
+
+        let size = simulation.plants()[0].size();
+        assert!(size >= previous && size <= 0.5);
+        previous = size;
+    }
+
+    let fertile = simulation.plants()[0].size();
+    let barren = simulation.plants()[1].size();
+    assert!(fertile > 0.45, "{}", fertile);
+    assert!(barren < fertile);
+}
+
+#[test]
+fn old_plants_die_and_feed_the_land() {
+    let config = SimConfig {
+        plant_lifespan: 50,
+        plant_death_fertility: 0.1,
+        fertility_regeneration: 0.0,
+        fertility_diffusion: 0.0,
+        ..SimConfig::default()
+    };
+    let mut simulation = empty_world(config);
+    let position = block_to_world(5, 5);
+    let before = simulation.terrain().block_at(position).unwrap().fertility;
+    for _ in 0..3 {
+        simulation.add_plant(Plant::new(position, 0.1));
+    }
+
+    for _ in 0..49 {
+        simulation.step(DT);
+    }
+    assert_eq!(simulation.plants().len(), 3);
+
+    simulation.step(DT);
+
+    assert!(simulation.plants().is_empty());
+    let after = simulation.terrain().block_at(position).unwrap().fertility;
+    assert!((after - before - 0.3).abs() < 1e-5);
+}
+
+#[test]
+fn first_plants_do_not_die_together() {
+    let config = SimConfig {
+        plant_lifespan: 200,
+        initial_plants: 500,
+        ..SimConfig::default()
+    };
+    let mut simulation = empty_world(config);
+
+    for _ in 0..100 {
+        simulation.step(DT);
+    }
+
+    let left = simulation.plants().len();
+    assert!(left > 100 && left < 400, "{}", left);
+}
+
+fn evolving() -> SimConfig {
+    SimConfig {
+        evolving_plants: true,
+        ..SimConfig::default()
+    }
+}
+
+// A plant with the middle genome of evolving plants, but `toxicity`.
+fn plant_with_toxicity(simulation: &Simulation, position: Vec2, toxicity: f32) -> Plant {
+    let mut values: Vec<f32> = simulation
+        .plant_layout()
+        .genes()
+        .iter()
+        .map(|gene| (gene.min + gene.max) / 2.0)
+        .collect();
+    values[TOXICITY] = toxicity;
+    Plant::with_genome(Genome::new(values), position, 0.2)
+}
+
+// How much energy an entity has after a step on top of a plant with `toxicity`.
+fn energy_after_eating(toxicity: f32) -> f32 {
+    let mut simulation = Simulation::with_config(0, evolving());
+    let plant = plant_with_toxicity(&simulation, Vec2::new(0.1, 0.0), toxicity);
+    simulation.add_plant(plant);
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], Vec2::ZERO, 5000.0);
+
+    simulation.step(DT);
+
+    assert!(simulation.plants().is_empty());
+    simulation.entities()[0].energy
+}
+
+#[test]
+fn plants_only_have_genomes_when_they_evolve() {
+    let simulation = Simulation::with_config(
+        1,
+        SimConfig {
+            initial_plants: 10,
+            ..SimConfig::default()
+        },
+    );
+
+    assert!(simulation.plant_layout().is_empty());
+    for plant in simulation.plants() {
+        assert!(plant.genome().values().is_empty());
+        assert_eq!(plant.seed_count(), 1);
+        assert_eq!(plant.toxicity(), 0.0);
+    }
+}
+
+#[test]
+fn seeds_inherit_the_genome() {
+    let config = SimConfig {
+        initial_plants: 300,
+        plant_growth_speed: 0.01,
+        ..evolving()
+    };
+    let mut simulation = Simulation::with_config(2, config);
+
+    for _ in 0..300 {
+        simulation.step(DT);
+    }
+
+    let layout = simulation.plant_layout();
+    assert_eq!(layout.genes()[SEED_COUNT].kind, GeneKind::Integer);
+    assert!(simulation.plants().len() > 300);
+    for plant in simulation.plants() {
+        assert_eq!(plant.genome().values().len(), layout.len());
+        for (value, gene) in plant.genome().values().iter().zip(layout.genes()) {
+            assert!(*value >= gene.min && *value <= gene.max, "{}", gene.name);
+        }
+        assert!((1..=4).contains(&plant.seed_count()));
+        assert!(plant.size() <= plant.max_size(simulation.config()));
+    }
+}
+
+#[test]
+fn toxic_plants_are_less_nourishing() {
+    let clean = energy_after_eating(0.0);
+    let toxic = energy_after_eating(0.25);
+
+    assert!(toxic < clean);
+}
+
+#[test]
+fn entities_following_the_rules_leave_poison_alone() {
+    let mut simulation = Simulation::with_config(0, evolving());
+    let plant = plant_with_toxicity(&simulation, Vec2::new(0.1, 0.0), 0.75);
+    simulation.add_plant(plant);
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], Vec2::ZERO, 5000.0);
+
+    simulation.step(DT);
+
+    assert_eq!(simulation.plants().len(), 1);
+}
+
+#[test]
+fn rejects_invalid_plant_settings() {
+    let invalid = [
+        r#"{ "plant_growth_speed": 2 }"#,
+        r#"{ "plant_max_size": 0 }"#,
+        r#"{ "plant_death_fertility": -1 }"#,
+        r#"{ "toxicity_growth_cost": 2 }"#,
+        r#"{ "poisonous_toxicity": -0.5 }"#,
+        r#"{ "plant_genes": [{ "name": "toxicity", "min": 0, "max": 2 }] }"#,
+        r#"{ "plant_genes": [{ "name": "max_size", "min": 0, "max": 1 }] }"#,
+        r#"{ "plant_genes": [{ "name": "a", "min": 0, "max": 1 }, { "name": "a", "min": 0, "max": 1 }] }"#,
+    ];
+
+    for json in invalid.iter() {
+        assert!(
+            matches!(SimConfig::from_json(json), Err(ConfigError::Invalid(_))),
+            "{}",
+            json
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! The render command buffer.
+
+mod common;
+
+use common::add_entity;
+use game::{
+    draw_world, rgba, species_color, world_to_screen, ColorMode, Plant, RenderBuffer, Shape,
+    Simulation, GEOMETRY_STRIDE, GRID_SIZE, PIXELS_PER_UNIT,
+};
+use glam::Vec2;
+
+#[test]
+fn packs_colors_as_rgba() {
+    assert_eq!(rgba(0x12, 0x34, 0x56, 0x78), 0x12345678);
+}
+
+#[test]
+fn draws_terrain_then_entities_then_plants() {
+    let mut simulation = Simulation::new(0);
+    add_entity(
+        &mut simulation,
+        &[0.5, 0.25, 1.0],
+        Vec2::new(3.0, -2.0),
+        100.0,
+    );
+    simulation.add_plant(Plant::new(Vec2::new(-7.0, 1.0), 0.2));
+
+    let mut buffer = RenderBuffer::new();
+    draw_world(&simulation, ColorMode::Traits, &mut buffer);
+
+    let blocks = GRID_SIZE * GRID_SIZE;
+    assert_eq!(buffer.len(), blocks + 2);
+    assert_eq!(buffer.geometry().len(), buffer.len() * GEOMETRY_STRIDE);
+
+    let entity = &buffer.geometry()[blocks * GEOMETRY_STRIDE..(blocks + 1) * GEOMETRY_STRIDE];
+    let center = world_to_screen(Vec2::new(3.0, -2.0));
+    assert_eq!(
+        entity,
+        &[
+            Shape::Circle as u32 as f32,
+            center.x,
+            center.y,
+            0.5 * PIXELS_PER_UNIT
+        ]
+    );
+    assert_eq!(buffer.colors()[blocks], rgba(127, 63, 255, 255));
+
+    let plant = &buffer.geometry()[(blocks + 1) * GEOMETRY_STRIDE..];
+    assert_eq!(plant[0], Shape::Rect as u32 as f32);
+
+    // Redrawing replaces the previous frame.
+    draw_world(&simulation, ColorMode::Traits, &mut buffer);
+    assert_eq!(buffer.len(), blocks + 2);
+}
+
+#[test]
+fn colors_entities_by_species() {
+    let mut simulation = Simulation::new(0);
+    add_entity(&mut simulation, &[0.5, 0.25, 1.0], Vec2::ZERO, 100.0);
+    let mut buffer = RenderBuffer::new();
+    let blocks = GRID_SIZE * GRID_SIZE;
+
+    // Not clustered yet.
+    draw_world(&simulation, ColorMode::Species, &mut buffer);
+    assert_eq!(buffer.colors()[blocks], rgba(127, 63, 255, 255));
+
+    simulation.cluster_species();
+    draw_world(&simulation, ColorMode::Species, &mut buffer);
+    let species = simulation.entities()[0].species().unwrap();
+    assert_eq!(buffer.colors()[blocks], species_color(species));
+}
+
+#[test]
+fn neighboring_species_get_distinct_colors() {
+    for id in 0..20 {
+        assert_ne!(species_color(id), species_color(id + 1));
+    }
+}
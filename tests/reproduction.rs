@@ -0,0 +1,151 @@
+//! Asexual and sexual reproduction.
+
+mod common;
+
+use common::{add_entity, DT};
+use game::{Behavior, Genome, GenomeLayout, Reproduction, SimConfig, Simulation};
+use glam::Vec2;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+fn sexual_config() -> SimConfig {
+    SimConfig {
+        reproduction: Reproduction::Sexual,
+        // Keep children identical to their parents' mix.
+        mutation_amount: 0.0,
+        ..SimConfig::default()
+    }
+}
+
+#[test]
+fn crossover_takes_every_gene_from_a_parent() {
+    let mut rng = Pcg32::seed_from_u64(0);
+    let a = Genome::new(vec![0.1; 50]);
+    let b = Genome::new(vec![0.9; 50]);
+
+    let child = a.crossover(&b, &mut rng);
+
+    assert!(child.values().iter().all(|v| *v == 0.1 || *v == 0.9));
+    assert!(child.values().contains(&0.1) && child.values().contains(&0.9));
+}
+
+#[test]
+fn distance_is_relative_to_the_ranges() {
+    let layout = GenomeLayout::new(&SimConfig::default());
+    let a = Genome::new(vec![0.1, 0.1, 0.1]);
+    let b = Genome::new(vec![1.0, 1.0, 1.0]);
+
+    assert_eq!(a.distance(&a, &layout), 0.0);
+    assert!((a.distance(&b, &layout) - 1.0).abs() < 1e-6);
+    assert!(
+        (a.distance(&Genome::new(vec![1.0, 0.1, 0.1]), &layout) - 1.0 / 3f32.sqrt()).abs() < 1e-6
+    );
+}
+
+#[test]
+fn distance_ignores_brain_weights() {
+    let layout = GenomeLayout::new(&SimConfig {
+        behavior: Behavior::Neural,
+        ..SimConfig::default()
+    });
+    let brain = layout.brain().unwrap();
+    let genome = |traits: f32, weights: f32| {
+        let mut values = vec![traits; layout.len()];
+        for value in values[brain.clone()].iter_mut() {
+            *value = weights;
+        }
+        Genome::new(values)
+    };
+
+    assert_eq!(genome(0.1, -4.0).distance(&genome(0.1, 4.0), &layout), 0.0);
+    assert!((genome(0.1, 0.0).distance(&genome(1.0, 0.0), &layout) - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn compatible_neighbors_have_a_child_together() {
+    let mut simulation = Simulation::with_config(0, sexual_config());
+    add_entity(&mut simulation, &[0.5, 0.1, 0.5], Vec2::ZERO, 20000.0);
+    add_entity(
+        &mut simulation,
+        &[0.5, 0.12, 0.5],
+        Vec2::new(1.2, 0.0),
+        20000.0,
+    );
+    let parents: Vec<_> = simulation.entities().iter().map(|e| e.id()).collect();
+
+    simulation.step(DT);
+
+    assert_eq!(simulation.entities().len(), 3);
+    let child = &simulation.entities()[2];
+    assert_eq!(child.parent_id(), Some(parents[0]));
+    assert_eq!(child.other_parent_id(), Some(parents[1]));
+    assert_eq!(
+        simulation.lineage().get(child.id()).unwrap().other_parent,
+        Some(parents[1])
+    );
+    assert!(child
+        .genome()
+        .values()
+        .iter()
+        .all(|v| [0.5, 0.1, 0.12].contains(v)));
+
+    // The child gets half of what each parent had left.
+    let parents_energy = simulation.entities()[0].energy + simulation.entities()[1].energy;
+    assert!((child.energy - parents_energy).abs() < 1e-2);
+}
+
+#[test]
+fn incompatible_or_lonely_entities_have_no_children() {
+    let mut simulation = Simulation::with_config(0, sexual_config());
+    add_entity(&mut simulation, &[0.5, 0.1, 0.5], Vec2::ZERO, 20000.0);
+    add_entity(
+        &mut simulation,
+        &[0.5, 1.0, 0.1],
+        Vec2::new(1.2, 0.0),
+        20000.0,
+    );
+    add_entity(
+        &mut simulation,
+        &[0.5, 0.1, 0.5],
+        Vec2::new(50.0, 0.0),
+        20000.0,
+    );
+
+    simulation.step(DT);
+
+    assert_eq!(simulation.entities().len(), 3);
+}
+
+#[test]
+fn asexual_children_have_one_parent() {
+    let mut simulation = Simulation::new(0);
+    add_entity(&mut simulation, &[0.5, 0.1, 0.5], Vec2::ZERO, 20000.0);
+
+    simulation.step(DT);
+
+    assert_eq!(simulation.entities().len(), 2);
+    assert!(simulation.entities()[1].parent_id().is_some());
+    assert_eq!(simulation.entities()[1].other_parent_id(), None);
+}
+
+#[test]
+fn sexual_worlds_are_reproducible() {
+    let run = || {
+        let config = SimConfig {
+            reproduction: Reproduction::Sexual,
+            mate_distance: 1.0,
+            mate_range: 10.0,
+            ..SimConfig::default()
+        };
+        let mut simulation = Simulation::with_config(6, config);
+        simulation.populate(200, 1000);
+        for _ in 0..300 {
+            simulation.step(DT);
+        }
+        simulation
+    };
+
+    let (a, b) = (run(), run());
+    assert_eq!(a.save(), b.save());
+    assert!(a.entities().iter().any(|e| e.other_parent_id().is_some()));
+}
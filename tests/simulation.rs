@@ -0,0 +1,169 @@
+//! Native tests for the headless simulation core.
+
+mod common;
+
+use common::add_entity;
+use game::{Plant, SimConfig, Simulation};
+use glam::Vec2;
+
+/// Every float of the world as raw bits, so that NaNs compare equal too.
+fn fingerprint(simulation: &Simulation) -> Vec<u32> {
+    let mut bits = vec![];
+
+    for entity in simulation.entities() {
+        bits.extend_from_slice(&[
+            entity.size().to_bits(),
+            entity.sense_distance().to_bits(),
+            entity.velocity_mag().to_bits(),
+            entity.position().x.to_bits(),
+            entity.position().y.to_bits(),
+            entity.energy.to_bits(),
+        ]);
+    }
+
+    for plant in simulation.plants() {
+        bits.extend_from_slice(&[
+            plant.position().x.to_bits(),
+            plant.position().y.to_bits(),
+            plant.size().to_bits(),
+        ]);
+    }
+
+    bits
+}
+
+fn run(seed: u64, ticks: usize) -> Simulation {
+    let mut simulation = Simulation::new(seed);
+    simulation.populate(100, 1000);
+
+    for tick in 0..ticks {
+        // Vary dt a little, like a real frame loop would.
+        simulation.step(1000.0 / 60.0 + (tick % 3) as f32);
+    }
+
+    simulation
+}
+
+#[test]
+fn entity_eats_overlapping_plant() {
+    let mut simulation = Simulation::new(0);
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], Vec2::ZERO, 5000.0);
+    simulation.add_plant(Plant::new(Vec2::new(0.1, 0.0), 0.2));
+
+    simulation.step(1000.0 / 60.0);
+
+    assert!(simulation.plants().is_empty());
+    assert!(simulation.entities()[0].energy > 5000.0);
+}
+
+#[test]
+fn starving_entity_dies() {
+    let mut simulation = Simulation::new(0);
+    add_entity(&mut simulation, &[0.5, 0.5, 0.5], Vec2::ZERO, 1.0);
+
+    simulation.step(1000.0 / 60.0);
+
+    assert!(simulation.entities().is_empty());
+}
+
+#[test]
+fn same_seed_produces_identical_worlds() {
+    let a = run(42, 300);
+    let b = run(42, 300);
+
+    assert!(!a.entities().is_empty());
+    assert_eq!(fingerprint(&a), fingerprint(&b));
+}
+
+#[test]
+fn different_seeds_produce_different_worlds() {
+    assert_ne!(fingerprint(&run(1, 10)), fingerprint(&run(2, 10)));
+}
+
+#[test]
+fn plant_counts_follow_the_plants() {
+    let mut simulation = run(5, 100);
+    simulation.step(1000.0 / 60.0);
+
+    let counted: u32 = simulation
+        .terrain()
+        .blocks()
+        .iter()
+        .flatten()
+        .map(|block| block.number_of_plants())
+        .sum();
+
+    let inside_the_grid = simulation
+        .plants()
+        .iter()
+        .filter(|plant| simulation.terrain().block_at(plant.position()).is_some())
+        .count();
+
+    assert!(counted > 0);
+    assert_eq!(counted as usize, inside_the_grid);
+}
+
+#[test]
+fn full_blocks_stop_growing() {
+    let config = SimConfig {
+        plant_carrying_capacity: 10,
+        plant_growth_rate: 1.0,
+        ..SimConfig::default()
+    };
+    let mut simulation = Simulation::with_config(9, config);
+    for _ in 0..10 {
+        simulation.add_plant(Plant::new(Vec2::ZERO, 0.1));
+    }
+
+    for _ in 0..50 {
+        simulation.step(1000.0 / 60.0);
+    }
+
+    let center = &simulation.terrain().blocks()[25][25];
+    assert!(center.number_of_plants() <= 10);
+    assert!(center.fertility > 0.5);
+}
+
+/// Advances a fresh world for `seconds` of real time split into frames at `hz`.
+fn run_at_frame_rate(hz: f32, seconds: f32) -> Simulation {
+    let mut simulation = Simulation::new(21);
+    simulation.populate(50, 500);
+
+    let frame = 1000.0 / hz;
+    for _ in 0..(seconds * hz).round() as usize {
+        simulation.advance(frame);
+    }
+
+    // Half a step more, so that rounding in the frame times can't change the step count.
+    simulation.advance(simulation.config().time_step / 2.0);
+
+    simulation
+}
+
+#[test]
+fn frame_rate_does_not_change_the_outcome() {
+    let at_60 = run_at_frame_rate(60.0, 2.0);
+
+    assert_eq!(at_60.tick(), 120);
+    for hz in [30.0, 144.0] {
+        let other = run_at_frame_rate(hz, 2.0);
+        assert_eq!(other.tick(), at_60.tick());
+        assert_eq!(fingerprint(&other), fingerprint(&at_60));
+    }
+}
+
+#[test]
+fn movement_scales_with_dt() {
+    let mut a = Simulation::new(0);
+    add_entity(&mut a, &[0.5, 0.5, 0.5], Vec2::ZERO, 5000.0);
+
+    let mut b = Simulation::new(0);
+    add_entity(&mut b, &[0.5, 0.5, 0.5], Vec2::ZERO, 5000.0);
+
+    a.step(10.0);
+    b.step(20.0);
+
+    let speed = a.entities()[0].max_speed(a.config());
+    assert!((a.entities()[0].position().length() - speed * 10.0).abs() < 1e-5);
+    assert!((b.entities()[0].position().length() - speed * 20.0).abs() < 1e-5);
+}
@@ -0,0 +1,168 @@
+//! Saving and loading a world must not change how it evolves.
+
+mod common;
+
+use common::{running_world, DT};
+use game::{Biome, Id, SimConfig, Simulation, SnapshotError, SNAPSHOT_VERSION};
+
+// Worlds saved by builds at snapshot versions 7 and 10: seed 3, 10 entities and 30 plants, 20
+// steps.
+const V7: &[u8] = include_bytes!("fixtures/v7.evol");
+const V7_JSON: &str = include_str!("fixtures/v7.json");
+const V10: &[u8] = include_bytes!("fixtures/v10.evol");
+const V10_JSON: &str = include_str!("fixtures/v10.json");
+
+fn step_both(a: &mut Simulation, b: &mut Simulation) {
+    for _ in 0..50 {
+        a.step(DT);
+        b.step(DT);
+    }
+}
+
+#[test]
+fn binary_round_trip_continues_identically() {
+    let mut original = running_world(11, 50, 300, 50);
+    let mut loaded = Simulation::load(&original.save()).unwrap();
+
+    step_both(&mut original, &mut loaded);
+
+    assert_eq!(original.save(), loaded.save());
+}
+
+#[test]
+fn json_round_trip_continues_identically() {
+    let mut original = running_world(11, 50, 300, 50);
+    let mut loaded = Simulation::load_json(&original.save_json()).unwrap();
+
+    step_both(&mut original, &mut loaded);
+
+    assert_eq!(original.save_json(), loaded.save_json());
+}
+
+#[test]
+fn rejects_foreign_bytes() {
+    assert!(matches!(
+        Simulation::load(b"not a snapshot"),
+        Err(SnapshotError::NotASnapshot)
+    ));
+}
+
+#[test]
+fn rejects_snapshots_from_newer_builds() {
+    let mut bytes = Simulation::new(0).save();
+    bytes[4..8].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+
+    assert!(matches!(
+        Simulation::load(&bytes),
+        Err(SnapshotError::UnsupportedVersion(_))
+    ));
+
+    let json = Simulation::new(0).save_json().replacen(
+        &format!("\"version\":{}", SNAPSHOT_VERSION),
+        &format!("\"version\":{}", SNAPSHOT_VERSION + 1),
+        1,
+    );
+
+    assert!(matches!(
+        Simulation::load_json(&json),
+        Err(SnapshotError::UnsupportedVersion(_))
+    ));
+}
+
+#[test]
+fn rejects_snapshots_with_an_invalid_config() {
+    let configs = [
+        SimConfig {
+            time_step: 0.0,
+            ..SimConfig::default()
+        },
+        SimConfig {
+            species_interval: 0,
+            ..SimConfig::default()
+        },
+    ];
+
+    for config in configs.iter() {
+        let simulation = Simulation::with_config(0, config.clone());
+
+        assert!(matches!(
+            Simulation::load(&simulation.save()),
+            Err(SnapshotError::Config(_))
+        ));
+        assert!(matches!(
+            Simulation::load_json(&simulation.save_json()),
+            Err(SnapshotError::Config(_))
+        ));
+    }
+}
+
+// Loads both encodings of a fixture, checks they hold the same world and that it steps.
+fn load_fixture(bytes: &[u8], json: &str) -> Simulation {
+    let mut binary = Simulation::load(bytes).unwrap();
+    let mut from_json = Simulation::load_json(json).unwrap();
+    assert_eq!(binary.save(), from_json.save());
+
+    let tick = binary.tick();
+    step_both(&mut binary, &mut from_json);
+    assert_eq!(binary.tick(), tick + 50);
+    assert_eq!(binary.save(), from_json.save());
+
+    Simulation::load(bytes).unwrap()
+}
+
+#[test]
+fn loads_snapshots_from_before_genomes() {
+    let simulation = load_fixture(V7, V7_JSON);
+
+    // The traits that were fields of entities are now their genome.
+    let saved: serde_json::Value = serde_json::from_str(V7_JSON).unwrap();
+    let entities = saved["simulation"]["entities"].as_array().unwrap();
+    assert_eq!(simulation.entities().len(), entities.len());
+    for (entity, saved) in simulation.entities().iter().zip(entities) {
+        assert_eq!(entity.size(), saved["size"].as_f64().unwrap() as f32);
+        assert_eq!(
+            entity.sense_distance(),
+            saved["sense_distance"].as_f64().unwrap() as f32
+        );
+        assert_eq!(
+            entity.velocity_mag(),
+            saved["velocity_mag"].as_f64().unwrap() as f32
+        );
+    }
+
+    // So are those of lineage records.
+    let records = saved["simulation"]["lineage"].as_array().unwrap();
+    assert_eq!(simulation.lineage().records().count(), records.len());
+    for saved in records {
+        let id: Id = serde_json::from_value(saved["id"].clone()).unwrap();
+        let traits: Vec<f32> = ["size", "sense_distance", "velocity_mag"]
+            .iter()
+            .map(|name| saved[*name].as_f64().unwrap() as f32)
+            .collect();
+        assert_eq!(
+            simulation.lineage().get(id).unwrap().genome.values(),
+            &traits[..]
+        );
+    }
+}
+
+#[test]
+fn loads_snapshots_from_before_biomes() {
+    let simulation = load_fixture(V10, V10_JSON);
+
+    // The terrain keeps its fertility and becomes flat radial land.
+    let saved: serde_json::Value = serde_json::from_str(V10_JSON).unwrap();
+    let rows = saved["simulation"]["terrain"]["blocks"].as_array().unwrap();
+    for (row, saved_row) in simulation.terrain().blocks().iter().zip(rows) {
+        for (block, saved) in row.iter().zip(saved_row.as_array().unwrap()) {
+            assert_eq!(block.fertility, saved["fertility"].as_f64().unwrap() as f32);
+            assert_eq!(block.elevation(), 1.0);
+            assert!(block.moisture() >= 0.0 && block.moisture() <= 1.0);
+            assert_ne!(block.biome(), Biome::Water);
+        }
+    }
+
+    let blocks = simulation.terrain().blocks();
+    let center = blocks[blocks.len() / 2][blocks.len() / 2].moisture();
+    assert!(center > blocks[0][0].moisture());
+}
@@ -0,0 +1,134 @@
+//! The spatial grid must agree with a plain linear scan.
+
+use game::SpatialGrid;
+use glam::Vec2;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+struct Item {
+    position: Vec2,
+    size: f32,
+}
+
+fn edge_distance(item: &Item, position: Vec2, size: f32) -> f32 {
+    item.position.distance(position) - (item.size + size)
+}
+
+fn linear_scan(
+    items: &[Item],
+    position: Vec2,
+    size: f32,
+    max_distance: f32,
+    skip: Option<usize>,
+) -> Option<(usize, f32)> {
+    let mut closest = None;
+    let mut closest_distance = f32::INFINITY;
+
+    for (j, item) in items.iter().enumerate() {
+        if Some(j) == skip {
+            continue;
+        }
+
+        let distance = edge_distance(item, position, size);
+        if distance < max_distance && distance < closest_distance {
+            closest = Some((j, distance));
+            closest_distance = distance;
+        }
+    }
+
+    closest
+}
+
+fn random_items(rng: &mut Pcg32, count: usize, extent: f32) -> Vec<Item> {
+    (0..count)
+        .map(|_| Item {
+            position: Vec2::new(
+                (rng.gen::<f32>() * 2.0 - 1.0) * extent,
+                (rng.gen::<f32>() * 2.0 - 1.0) * extent,
+            ),
+            size: rng.gen::<f32>(),
+        })
+        .collect()
+}
+
+fn grid_of(items: &[Item]) -> SpatialGrid {
+    let mut grid = SpatialGrid::new(5.0);
+    for (i, item) in items.iter().enumerate() {
+        grid.insert(i, item.position, item.size);
+    }
+    grid
+}
+
+#[test]
+fn closest_matches_linear_scan() {
+    let mut rng = Pcg32::seed_from_u64(3);
+    let items = random_items(&mut rng, 2000, 100.0);
+    let grid = grid_of(&items);
+
+    for _ in 0..2000 {
+        let position = Vec2::new(
+            (rng.gen::<f32>() * 2.0 - 1.0) * 150.0,
+            (rng.gen::<f32>() * 2.0 - 1.0) * 150.0,
+        );
+        let size = rng.gen::<f32>();
+        let max_distance = rng.gen::<f32>() * 100.0;
+
+        let expected = linear_scan(&items, position, size, max_distance, None);
+        let actual = grid.closest(position, size, max_distance, |j| {
+            Some(edge_distance(&items[j], position, size))
+        });
+
+        assert_eq!(expected, actual);
+    }
+}
+
+#[test]
+fn ties_go_to_the_lowest_index_and_skipped_items_are_ignored() {
+    let mut items = vec![];
+    for _ in 0..10 {
+        items.push(Item {
+            position: Vec2::new(12.0, -3.0),
+            size: 0.5,
+        });
+    }
+    items.push(Item {
+        position: Vec2::new(f32::NAN, 0.0),
+        size: 0.5,
+    });
+    let grid = grid_of(&items);
+
+    for skip in [None, Some(0), Some(4)] {
+        let expected = linear_scan(&items, Vec2::ZERO, 0.5, 100.0, skip);
+        let actual = grid.closest(Vec2::ZERO, 0.5, 100.0, |j| {
+            if Some(j) == skip {
+                None
+            } else {
+                Some(edge_distance(&items[j], Vec2::ZERO, 0.5))
+            }
+        });
+
+        assert_eq!(expected, actual);
+    }
+}
+
+#[test]
+fn relocated_items_are_found_at_their_new_position() {
+    let mut rng = Pcg32::seed_from_u64(5);
+    let mut items = random_items(&mut rng, 500, 50.0);
+    let mut grid = grid_of(&items);
+
+    for i in 0..items.len() {
+        let from = items[i].position;
+        let to = from + Vec2::new(rng.gen::<f32>() * 20.0, rng.gen::<f32>() * -20.0);
+        items[i].position = to;
+        grid.relocate(i, from, to, items[i].size);
+
+        let position = Vec2::new(rng.gen::<f32>() * 60.0, rng.gen::<f32>() * -60.0);
+        let expected = linear_scan(&items, position, 0.3, 30.0, None);
+        let actual = grid.closest(position, 0.3, 30.0, |j| {
+            Some(edge_distance(&items[j], position, 0.3))
+        });
+
+        assert_eq!(expected, actual);
+    }
+}
@@ -0,0 +1,156 @@
+//! Species clustering.
+
+mod common;
+
+use std::collections::HashMap;
+
+use common::{add_entity, DT};
+use game::{SimConfig, Simulation};
+use glam::Vec2;
+
+#[test]
+fn separates_distant_groups() {
+    let mut simulation = Simulation::new(0);
+    add_entity(
+        &mut simulation,
+        &[0.2, 0.2, 0.2],
+        Vec2::new(0.0, 0.0),
+        100.0,
+    );
+    add_entity(
+        &mut simulation,
+        &[0.22, 0.2, 0.2],
+        Vec2::new(10.0, 0.0),
+        100.0,
+    );
+    add_entity(
+        &mut simulation,
+        &[0.9, 0.9, 0.9],
+        Vec2::new(20.0, 0.0),
+        100.0,
+    );
+
+    simulation.cluster_species();
+
+    let species = simulation.species().species();
+    assert_eq!(species.len(), 2);
+    assert_eq!(species[0].population(), 2);
+    assert!((species[0].centroid().get(0) - 0.21).abs() < 1e-6);
+    assert_eq!(species[1].population(), 1);
+
+    let entities = simulation.entities();
+    assert_eq!(entities[0].species(), entities[1].species());
+    assert_ne!(entities[0].species(), entities[2].species());
+}
+
+#[test]
+fn ids_survive_reclustering() {
+    let mut simulation = Simulation::new(0);
+    add_entity(
+        &mut simulation,
+        &[0.2, 0.2, 0.2],
+        Vec2::new(0.0, 0.0),
+        100.0,
+    );
+    add_entity(
+        &mut simulation,
+        &[0.9, 0.9, 0.9],
+        Vec2::new(20.0, 0.0),
+        100.0,
+    );
+    simulation.cluster_species();
+    let before = simulation.species().species().to_vec();
+
+    simulation.cluster_species();
+
+    assert_eq!(simulation.species().species(), &before[..]);
+}
+
+#[test]
+fn species_without_members_go_extinct() {
+    let mut simulation = Simulation::new(0);
+    add_entity(
+        &mut simulation,
+        &[0.2, 0.2, 0.2],
+        Vec2::new(0.0, 0.0),
+        100.0,
+    );
+    // Starves on the next step.
+    add_entity(
+        &mut simulation,
+        &[0.9, 0.9, 0.9],
+        Vec2::new(20.0, 0.0),
+        0.001,
+    );
+    simulation.cluster_species();
+    assert_eq!(simulation.species().species().len(), 2);
+
+    simulation.step(DT);
+    simulation.cluster_species();
+
+    assert_eq!(simulation.species().species().len(), 1);
+    assert_eq!(simulation.species().species()[0].id(), 0);
+}
+
+#[test]
+fn clusters_periodically_and_keeps_track() {
+    let config = SimConfig {
+        species_interval: 50,
+        ..SimConfig::default()
+    };
+    let mut simulation = Simulation::with_config(1, config);
+    simulation.populate(200, 1000);
+
+    for _ in 0..50 {
+        simulation.step(DT);
+    }
+    let first: HashMap<_, _> = simulation
+        .species()
+        .species()
+        .iter()
+        .map(|species| (species.id(), species.birth_tick()))
+        .collect();
+    assert!(simulation.entities().iter().all(|e| e.species().is_some()));
+    let population: usize = simulation
+        .species()
+        .species()
+        .iter()
+        .map(|species| species.population())
+        .sum();
+    assert_eq!(population, simulation.entities().len());
+
+    for _ in 0..100 {
+        simulation.step(DT);
+        // Newborns belong to the species of their parent until the next clustering.
+        assert!(simulation.entities().iter().all(|e| e.species().is_some()));
+    }
+
+    let mut survivors = 0;
+    for species in simulation.species().species() {
+        if let Some(birth_tick) = first.get(&species.id()) {
+            assert_eq!(species.birth_tick(), *birth_tick);
+            survivors += 1;
+        } else {
+            assert!(species.birth_tick() > 50);
+        }
+    }
+    assert!(survivors > 0);
+}
+
+#[test]
+fn json_lists_every_species() {
+    let mut simulation = Simulation::new(2);
+    simulation.populate(100, 0);
+    simulation.cluster_species();
+
+    let species: Vec<serde_json::Value> =
+        serde_json::from_str(&simulation.species().to_json(simulation.genome_layout())).unwrap();
+
+    assert_eq!(species.len(), simulation.species().species().len());
+    let population: u64 = species
+        .iter()
+        .map(|species| species["population"].as_u64().unwrap())
+        .sum();
+    assert_eq!(population, 100);
+    assert!(species[0]["centroid"]["sense_distance"].is_number());
+}
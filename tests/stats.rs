@@ -0,0 +1,74 @@
+//! Per-tick statistics.
+
+use game::{Entity, Genome, Simulation, Stats, Trait, HISTOGRAM_BINS};
+use glam::Vec2;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+
+fn entities(sizes: &[f32]) -> Vec<Entity> {
+    let mut rng = Pcg32::seed_from_u64(0);
+    sizes
+        .iter()
+        .map(|&size| {
+            Entity::new(
+                Genome::new(vec![size, 0.5, 0.25]),
+                Vec2::ZERO,
+                100.0,
+                &mut rng,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn records_means_variances_and_histograms() {
+    let mut stats = Stats::new(10);
+    stats.record(1, &entities(&[0.2, 0.4, 0.95]), &[]);
+
+    let sample = stats.latest().unwrap();
+    assert_eq!(sample.population, 3);
+    assert_eq!(sample.total_energy, 300.0);
+
+    let size = sample.trait_stats(Trait::Size);
+    assert!((size.mean - 0.5166667).abs() < 1e-6);
+    assert!((size.variance - 0.1005556).abs() < 1e-6);
+    assert_eq!(size.histogram[2], 1);
+    assert_eq!(size.histogram[4], 1);
+    assert_eq!(size.histogram[HISTOGRAM_BINS - 1], 1);
+
+    let velocity = sample.trait_stats(Trait::VelocityMag);
+    assert_eq!(velocity.mean, 0.25);
+    assert_eq!(velocity.variance, 0.0);
+}
+
+#[test]
+fn keeps_only_the_latest_samples() {
+    let mut stats = Stats::new(3);
+    for tick in 0..5 {
+        stats.record(tick, &[], &[]);
+    }
+
+    assert_eq!(stats.series(|sample| sample.tick), vec![2, 3, 4]);
+    assert_eq!(stats.histograms(Trait::Size).len(), 3 * HISTOGRAM_BINS);
+}
+
+#[test]
+fn simulation_records_one_sample_per_tick() {
+    let mut simulation = Simulation::new(1);
+    simulation.populate(20, 100);
+
+    for _ in 0..7 {
+        simulation.step(1000.0 / 60.0);
+    }
+
+    assert_eq!(simulation.stats().len(), 7);
+
+    let csv = simulation.stats().to_csv();
+    let mut lines = csv.lines();
+    let header = lines.next().unwrap();
+    assert!(header.starts_with("tick,population,plants,total_energy,size_mean"));
+
+    let columns = header.split(',').count();
+    assert_eq!(lines.clone().count(), 7);
+    assert!(lines.all(|line| line.split(',').count() == columns));
+}
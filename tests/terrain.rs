@@ -0,0 +1,197 @@
+//! Terrain generation and biomes.
+
+use game::{
+    block_to_world, fractal_noise, Biome, Clock, ConfigError, SimConfig, Simulation, Terrain,
+    TerrainKind, GRID_SIZE, MAX_FERTILITY,
+};
+
+fn noise_config() -> SimConfig {
+    SimConfig {
+        terrain: TerrainKind::Noise,
+        ..SimConfig::default()
+    }
+}
+
+fn elevations(terrain: &Terrain) -> Vec<f32> {
+    terrain
+        .blocks()
+        .iter()
+        .flatten()
+        .map(|block| block.elevation())
+        .collect()
+}
+
+#[test]
+fn fractal_noise_is_smooth_and_in_range() {
+    for i in 0..1000 {
+        let x = i as f32 * 0.137;
+        let y = i as f32 * 0.071;
+        let value = fractal_noise(5, x, y, 4);
+
+        assert!((0.0..=1.0).contains(&value));
+        assert!((fractal_noise(5, x + 0.001, y, 4) - value).abs() < 0.01);
+    }
+    assert_ne!(fractal_noise(5, 0.5, 0.5, 4), fractal_noise(6, 0.5, 0.5, 4));
+}
+
+#[test]
+fn noise_terrain_depends_only_on_the_seed() {
+    let config = noise_config();
+
+    let a = Terrain::generate(&config, 1);
+
+    assert_eq!(elevations(&a), elevations(&Terrain::generate(&config, 1)));
+    assert_ne!(elevations(&a), elevations(&Terrain::generate(&config, 2)));
+}
+
+#[test]
+fn blocks_match_their_biome() {
+    let config = noise_config();
+    let terrain = Terrain::generate(&config, 3);
+    let mut seen = vec![];
+
+    for block in terrain.blocks().iter().flatten() {
+        assert!((0.0..=1.0).contains(&block.elevation()));
+        assert!((0.0..=1.0).contains(&block.moisture()));
+        assert_eq!(
+            block.biome(),
+            Biome::classify(block.elevation(), block.moisture(), config.water_level)
+        );
+
+        if block.biome() == Biome::Water {
+            assert_eq!(block.fertility, 0.0);
+        } else {
+            assert_eq!(block.fertility, block.moisture());
+        }
+
+        if !seen.contains(&block.biome()) {
+            seen.push(block.biome());
+        }
+    }
+
+    assert_eq!(seen.len(), 4, "{:?}", seen);
+}
+
+#[test]
+fn radial_terrain_has_no_water() {
+    let terrain = Terrain::generate(&SimConfig::default(), 3);
+    let center = &terrain.blocks()[GRID_SIZE / 2][GRID_SIZE / 2];
+    let corner = &terrain.blocks()[0][0];
+
+    assert!(terrain
+        .blocks()
+        .iter()
+        .flatten()
+        .all(|block| block.biome() != Biome::Water && block.elevation() == 1.0));
+    assert_eq!(center.biome(), Biome::Forest);
+    assert_eq!(corner.biome(), Biome::Desert);
+    assert!(center.fertility > corner.fertility);
+}
+
+#[test]
+fn terrain_seed_keeps_the_landscape_across_worlds() {
+    let config = SimConfig {
+        terrain_seed: Some(8),
+        ..noise_config()
+    };
+
+    let a = Simulation::with_config(1, config.clone());
+    let b = Simulation::with_config(2, config);
+
+    assert_eq!(elevations(a.terrain()), elevations(b.terrain()));
+}
+
+#[test]
+fn rejects_invalid_terrain_settings() {
+    let invalid = [
+        r#"{ "terrain_scale": 0 }"#,
+        r#"{ "terrain_octaves": 0 }"#,
+        r#"{ "water_level": 1.5 }"#,
+        r#"{ "fertility_regeneration": -0.1 }"#,
+        r#"{ "fertility_diffusion": 2 }"#,
+    ];
+
+    for json in invalid.iter() {
+        assert!(
+            matches!(SimConfig::from_json(json), Err(ConfigError::Invalid(_))),
+            "{}",
+            json
+        );
+    }
+    assert_eq!(
+        SimConfig::from_json(r#"{ "terrain": "noise" }"#)
+            .unwrap()
+            .terrain,
+        TerrainKind::Noise
+    );
+}
+
+fn total_fertility(terrain: &Terrain) -> f32 {
+    terrain.blocks().iter().flatten().map(|b| b.fertility).sum()
+}
+
+#[test]
+fn fertility_regenerates_towards_the_base() {
+    let config = SimConfig {
+        fertility_regeneration: 0.1,
+        fertility_diffusion: 0.0,
+        ..SimConfig::default()
+    };
+    let mut terrain = Terrain::generate(&config, 0);
+    let center = block_to_world(GRID_SIZE / 2, GRID_SIZE / 2);
+    let base = terrain.block_at(center).unwrap().base_fertility();
+    terrain.block_at_mut(center).unwrap().fertility = 0.0;
+
+    for _ in 0..100 {
+        terrain.update(&config, &Clock::new(0, &config));
+    }
+
+    assert!((terrain.block_at(center).unwrap().fertility - base).abs() < 1e-3);
+}
+
+#[test]
+fn fertility_spreads_without_being_lost() {
+    let config = SimConfig {
+        fertility_regeneration: 0.0,
+        fertility_diffusion: 0.5,
+        ..SimConfig::default()
+    };
+    let mut terrain = Terrain::generate(&config, 0);
+    let corner = block_to_world(0, 0);
+    let neighbor = block_to_world(1, 0);
+    terrain.block_at_mut(corner).unwrap().fertility = 0.0;
+    let gap = terrain.block_at(neighbor).unwrap().fertility;
+    let total = total_fertility(&terrain);
+
+    terrain.update(&config, &Clock::new(0, &config));
+
+    let gained = terrain.block_at(corner).unwrap().fertility;
+    assert!(gained > 0.0 && gained < gap);
+
+    for _ in 0..10 {
+        terrain.update(&config, &Clock::new(0, &config));
+    }
+    assert!((total_fertility(&terrain) - total).abs() < 1e-2);
+}
+
+#[test]
+fn fertility_stays_in_range_and_off_the_water() {
+    let config = SimConfig {
+        death_fertility: 5.0,
+        initial_entities: 100,
+        initial_plants: 500,
+        ..noise_config()
+    };
+    let mut simulation = Simulation::with_config(4, config);
+
+    for _ in 0..300 {
+        simulation.step(1000.0 / 60.0);
+
+        for block in simulation.terrain().blocks().iter().flatten() {
+            assert!((0.0..=MAX_FERTILITY).contains(&block.fertility));
+            if block.biome() == Biome::Water {
+                assert_eq!(block.fertility, 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! The world coordinate helpers must agree with each other.
+
+use game::{
+    block_to_world, screen_to_world, world_to_block, world_to_screen, BLOCK_SIZE, GRID_SIZE,
+    WORLD_HALF_EXTENT,
+};
+use glam::Vec2;
+
+#[test]
+fn block_centers_map_back_to_their_block() {
+    for x in 0..GRID_SIZE {
+        for y in 0..GRID_SIZE {
+            assert_eq!(world_to_block(block_to_world(x, y)), Some((x, y)));
+        }
+    }
+}
+
+#[test]
+fn blocks_cover_exactly_the_terrain() {
+    let edge = WORLD_HALF_EXTENT;
+    let inside = edge - BLOCK_SIZE / 100.0;
+
+    assert_eq!(world_to_block(Vec2::new(-edge, -edge)), Some((0, 0)));
+    assert_eq!(
+        world_to_block(Vec2::new(inside, inside)),
+        Some((GRID_SIZE - 1, GRID_SIZE - 1))
+    );
+    assert_eq!(world_to_block(Vec2::new(edge, 0.0)), None);
+    assert_eq!(world_to_block(Vec2::new(0.0, -edge - 0.01)), None);
+    assert_eq!(world_to_block(Vec2::new(-1000.0, 0.0)), None);
+    assert_eq!(world_to_block(Vec2::new(f32::NAN, 0.0)), None);
+}
+
+#[test]
+fn screen_to_world_undoes_world_to_screen() {
+    let position = Vec2::new(12.5, -3.25);
+    let camera = Vec2::new(300.0, -40.0);
+    let scale = 2.0;
+
+    let screen = world_to_screen(position) * scale + camera;
+
+    assert!(screen_to_world(screen, camera, scale).abs_diff_eq(position, 1e-4));
+}